@@ -1,7 +1,28 @@
-//! Accessor macro to get a value form a MySQL Row
+//! Accessor macros to get a value from a MySQL Row
+
+/// Get a value from a MySQL row by column index or name.
+///
+/// Panics, if the column doesn't exist or the value can't be converted.
+/// Use [mysql_row_get] for a fallible lookup.
+#[deprecated(note = "panics on missing columns and bad values, use `mysql_row_get!` instead")]
 #[macro_export]
 macro_rules! mysql_row_try_get {
     ($var: tt, $index: expr) => {
         $var.get_opt($index).unwrap()
     };
 }
+
+/// Get a value from a [Row](crate::row::Row) by column index or name.
+///
+/// Returns `Result<Option<T>, ToqlMySqlAsyncError>` with `None` for `NULL`.
+///
+/// ```rust
+/// let id: Option<u64> = mysql_row_get!(row, "id")?;
+/// let name: Option<String> = mysql_row_get!(row, 1)?;
+/// ```
+#[macro_export]
+macro_rules! mysql_row_get {
+    ($var: tt, $column: expr) => {
+        $var.try_get($column)
+    };
+}
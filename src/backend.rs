@@ -2,17 +2,30 @@
 use toql::{
     backend::Backend,
     prelude::{
-        log_literal_sql, log_mut_sql, log_sql, val, AliasFormat, Cache, Context, Page, Sql, SqlArg,
+        log_literal_sql, log_mut_sql, log_sql, AliasFormat, Cache, Context, Page, Sql, SqlArg,
         TableMapperRegistry, ToqlError,
     },
     sql_builder::build_result::BuildResult,
 };
 
-use crate::{error::ToqlMySqlAsyncError, queryable::Queryable, result::Result, row::Row};
+use crate::{
+    convert::{probe, Probe},
+    error::ToqlMySqlAsyncError,
+    insert::{count_placeholders, InsertStatement},
+    keyset::{keyset_sql, row_cursor, Cursor},
+    paging::{unlimited_count_sql, PageCountStrategy},
+    queryable::Queryable,
+    result::Result,
+    row::{FieldPaths, Row},
+    settings::Settings,
+};
 
 use std::{
     collections::{HashMap, HashSet},
-    sync::{RwLockReadGuard, RwLockWriteGuard},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLockReadGuard, RwLockWriteGuard,
+    },
 };
 
 use async_trait::async_trait;
@@ -28,6 +41,279 @@ where
     pub conn: C,
     pub(crate) context: Context,
     pub(crate) cache: &'a Cache,
+    pub(crate) settings: Settings,
+    // Window count of the last counted page, see `prepare_page`
+    pub(crate) window_count: Option<u64>,
+    // Set by `load_paged` if the next select is a keyset page after the given cursor
+    pub(crate) keyset_pending: Option<Option<Cursor>>,
+    pub(crate) keyset_cursor: Option<Cursor>,
+    // Set by the load functions to report errors with the field paths of this mapper
+    pub(crate) mapper: Option<String>,
+}
+
+/// Server and table properties, that are looked up once and shared by all connections.
+///
+/// The cache belongs to the settings of a connection or pool wrapper,
+/// the connections of a pool share it.
+#[derive(Debug, Default)]
+pub(crate) struct SchemaCache {
+    autoinc_lock_mode: Mutex<Option<Option<u8>>>,
+    // Auto increment columns by database and table, unqualified tables have no database
+    auto_increment_columns: Mutex<HashMap<(Option<String>, String), Option<AutoIncrementColumn>>>,
+    // Field paths by mapper, table alias and column
+    field_paths: Mutex<HashMap<(String, String, String), Option<String>>>,
+}
+
+impl SchemaCache {
+    /// Forget all looked up properties, e.g. after a schema change.
+    pub(crate) fn clear(&self) {
+        // A poisoned cache is cleared too
+        *self
+            .autoinc_lock_mode
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+        self.auto_increment_columns
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+        self.field_paths
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+    }
+}
+
+// Savepoints of single row inserts are numbered, so they never replace an outer savepoint
+static INSERT_SAVEPOINT: AtomicU64 = AtomicU64::new(0);
+
+/// Auto increment column of a table.
+#[derive(Debug, Clone)]
+pub(crate) struct AutoIncrementColumn {
+    name: String,
+    signed: bool,
+}
+
+impl<'a, C> MySqlAsyncBackend<'a, C>
+where
+    C: Queryable + Send,
+{
+    pub(crate) fn new(conn: C, cache: &'a Cache, context: Context, settings: Settings) -> Self {
+        MySqlAsyncBackend {
+            conn,
+            cache,
+            context,
+            settings,
+            window_count: None,
+            keyset_pending: None,
+            keyset_cursor: None,
+            mapper: None,
+        }
+    }
+
+    /// Add statement and arguments to an error of the database.
+    /// The statement is copied into the error context only, if it fails.
+    fn statement_error<'s>(
+        &self,
+        sql: &'s str,
+        args: &[mysql_async::Value],
+    ) -> impl FnOnce(mysql_async::Error) -> ToqlMySqlAsyncError + 's {
+        // Arguments are moved into the statement, so they are recorded before, if enabled
+        let args = self.settings.error_args.record(args);
+        move |err| ToqlMySqlAsyncError::statement(err, sql, args)
+    }
+
+    /// Values to bind.
+    fn values(&self, args: Vec<SqlArg>) -> Vec<mysql_async::Value> {
+        crate::sql_arg::values_from(args)
+    }
+
+    /// Resolve the columns of the registered converters
+    /// and convert the inserted or assigned arguments of a statement.
+    fn convert_args(&self, sql: &str, args: Vec<SqlArg>) -> Result<Vec<SqlArg>> {
+        let converters = &self.settings.row.converters;
+        if converters.is_empty() {
+            return Ok(args);
+        }
+        let registry = self.cache.registry.read().map_err(ToqlError::from)?;
+        converters.resolve(&registry, &self.context.roles);
+        Ok(converters.convert_args(sql, args))
+    }
+
+    /// Auto increment column of a table or `None`, if the table has none.
+    async fn auto_increment_column(&mut self, table: &str) -> Result<Option<AutoIncrementColumn>> {
+        let key = match table.split_once('.') {
+            Some((database, table)) => (Some(database.to_string()), table.to_string()),
+            None => (None, table.to_string()),
+        };
+        if let Some(column) = self
+            .settings
+            .schema
+            .auto_increment_columns
+            .lock()
+            .map_err(ToqlError::from)?
+            .get(&key)
+        {
+            return Ok(column.clone());
+        }
+        // Unlike information_schema `SHOW COLUMNS` works for temporary tables too
+        let columns_sql = format!(
+            "SHOW COLUMNS FROM `{}` WHERE Extra LIKE '%auto_increment%'",
+            table.replace('`', "``").replace('.', "`.`")
+        );
+        log_literal_sql!(&columns_sql);
+        let statement_error = self.statement_error(&columns_sql, &[]);
+        let row: Option<mysql_async::Row> = self
+            .conn
+            .query_first(columns_sql.as_str())
+            .await
+            .map_err(statement_error)?;
+        let column = match row {
+            Some(row) => {
+                let name = row
+                    .get_opt::<String, _>("Field")
+                    .ok_or_else(|| ToqlMySqlAsyncError::ColumnNotFoundError("Field".to_string()))??;
+                // The type carries the sign, e.g. `bigint(20) unsigned` or `int unsigned`
+                let column_type = row
+                    .get_opt::<String, _>("Type")
+                    .ok_or_else(|| ToqlMySqlAsyncError::ColumnNotFoundError("Type".to_string()))??;
+                let signed = !column_type.to_ascii_lowercase().contains("unsigned");
+                Some(AutoIncrementColumn { name, signed })
+            }
+            None => None,
+        };
+        self.settings
+            .schema
+            .auto_increment_columns
+            .lock()
+            .map_err(ToqlError::from)?
+            .insert(key, column.clone());
+        Ok(column)
+    }
+
+    /// The server's `innodb_autoinc_lock_mode`, errors of the query are returned.
+    async fn autoinc_lock_mode(&mut self) -> Result<Option<u8>> {
+        if let Some(mode) = *self
+            .settings
+            .schema
+            .autoinc_lock_mode
+            .lock()
+            .map_err(ToqlError::from)?
+        {
+            return Ok(mode);
+        }
+        let lock_mode_sql = "SELECT @@innodb_autoinc_lock_mode";
+        log_literal_sql!(lock_mode_sql);
+        let statement_error = self.statement_error(lock_mode_sql, &[]);
+        let mode: Option<u8> = self
+            .conn
+            .query_first(lock_mode_sql)
+            .await
+            .map_err(statement_error)?;
+        *self
+            .settings
+            .schema
+            .autoinc_lock_mode
+            .lock()
+            .map_err(ToqlError::from)? = Some(mode);
+        Ok(mode)
+    }
+
+    /// Returns true, if the ids of a multi row insert are guaranteed to be contiguous.
+    async fn contiguous_ids(&mut self) -> Result<bool> {
+        // Interleaved lock mode does not reserve consecutive ids
+        Ok(self.autoinc_lock_mode().await? != Some(2))
+    }
+
+    /// Insert row by row and collect the id of every row.
+    ///
+    /// Like the multi row insert the single row inserts succeed or fail together:
+    /// Inside a transaction they run in a savepoint, otherwise in their own transaction.
+    async fn insert_rows(
+        &mut self,
+        statement: &InsertStatement<'_>,
+        args: Vec<SqlArg>,
+        auto_increment_column: &AutoIncrementColumn,
+    ) -> Result<Vec<SqlArg>> {
+        let (begin, commit, rollback) = if self.conn.in_transaction() {
+            let savepoint = format!(
+                "toql_insert_{}",
+                INSERT_SAVEPOINT.fetch_add(1, Ordering::Relaxed)
+            );
+            (
+                format!("SAVEPOINT {}", savepoint),
+                format!("RELEASE SAVEPOINT {}", savepoint),
+                format!("ROLLBACK TO SAVEPOINT {}", savepoint),
+            )
+        } else {
+            (
+                "START TRANSACTION".to_string(),
+                "COMMIT".to_string(),
+                "ROLLBACK".to_string(),
+            )
+        };
+        self.literal_sql(&begin).await?;
+        match self
+            .insert_each_row(statement, args, auto_increment_column)
+            .await
+        {
+            Ok(ids) => {
+                self.literal_sql(&commit).await?;
+                Ok(ids)
+            }
+            Err(err) => {
+                // Keep the error of the insert, it's more meaningful
+                if let Err(rollback_err) = self.literal_sql(&rollback).await {
+                    tracing::warn!("Rollback of single row inserts failed: {}", rollback_err);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Run a statement without arguments, e.g. to control a transaction.
+    pub(crate) async fn literal_sql(&mut self, stmt: &str) -> Result<()> {
+        log_literal_sql!(stmt);
+        let statement_error = self.statement_error(stmt, &[]);
+        self.conn.query_drop(stmt).await.map_err(statement_error)?;
+        Ok(())
+    }
+
+    async fn insert_each_row(
+        &mut self,
+        statement: &InsertStatement<'_>,
+        args: Vec<SqlArg>,
+        auto_increment_column: &AutoIncrementColumn,
+    ) -> Result<Vec<SqlArg>> {
+        let mut args = args.into_iter();
+        let row_args = statement
+            .rows
+            .iter()
+            .map(|r| args.by_ref().take(count_placeholders(r)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let tail_args = args.collect::<Vec<_>>();
+
+        let mut ids = Vec::with_capacity(statement.rows.len());
+        for (row, mut args) in statement.rows.iter().zip(row_args) {
+            args.extend(tail_args.iter().cloned());
+            let sql = Sql(statement.row_sql(row), args);
+            log_mut_sql!(&sql);
+            let Sql(sql_stmt, args) = sql;
+            let args = self.values(args);
+            let statement_error = self.statement_error(&sql_stmt, &args);
+            self.conn
+                .exec_drop(sql_stmt.as_str(), args)
+                .await
+                .map_err(statement_error)?;
+            ids.extend(generated_ids(
+                self.conn.affected_rows(),
+                self.conn.last_insert_id(),
+                auto_increment_column.signed,
+            ));
+        }
+        // Greatest id first
+        ids.reverse();
+        Ok(ids)
+    }
 }
 
 /// Interface for Toql functions
@@ -55,13 +341,68 @@ where
     }
 
     async fn select_sql(&mut self, sql: Sql) -> Result<Vec<Row>> {
+        // The statement tells, if it's a counted page, so a failed select leaves no state behind
+        let window_count = has_window_count(&sql.0);
+        // Only the first select of a keyset page loads the entities of the page
+        let (sql, cursor_columns) = match self.keyset_pending.take() {
+            Some(after) => keyset_sql(sql, after.as_ref())?,
+            None => (sql, 0),
+        };
         log_sql!(&sql);
         let Sql(sql_stmt, args) = sql;
 
-        let args = crate::sql_arg::values_from_ref(&args);
-        let rows = self.conn.exec(sql_stmt, args).await?;
+        let args = self.values(self.convert_args(&sql_stmt, args)?);
+        let statement_error = self.statement_error(&sql_stmt, &args);
+        let mut rows: Vec<mysql_async::Row> = self
+            .conn
+            .exec(sql_stmt.as_str(), args)
+            .await
+            .map_err(statement_error)?;
+
+        if window_count {
+            // An empty page has no count, it's counted separately
+            self.window_count = None;
+            if let Some(row) = rows.first() {
+                let window_column = row.columns_ref().first().map(|c| c.name_str());
+                if window_column.as_deref() != Some(WINDOW_COUNT_ALIAS) {
+                    return Err(ToqlMySqlAsyncError::ColumnNotFoundError(
+                        WINDOW_COUNT_ALIAS.to_string(),
+                    ));
+                }
+                self.window_count = row.get_opt::<u64, _>(0).transpose()?;
+            }
+            rows = rows.into_iter().map(|r| without_columns(r, 1)).collect();
+        }
+
+        if cursor_columns > 0 {
+            self.keyset_cursor = match rows.last() {
+                Some(row) => Some(row_cursor(row, cursor_columns)?),
+                None => None,
+            };
+            rows = rows
+                .into_iter()
+                .map(|r| without_columns(r, cursor_columns))
+                .collect();
+        }
 
-        Ok(rows.into_iter().map(|r| Row(r)).collect::<Vec<Row>>()) // Wrap new type
+        let field_paths = match (&self.mapper, rows.first()) {
+            (Some(mapper), Some(row)) => {
+                let registry = self.cache.registry.read().map_err(ToqlError::from)?;
+                Some(field_paths(
+                    &registry,
+                    &self.context.roles,
+                    &self.context.alias_format,
+                    &self.settings.schema,
+                    mapper,
+                    row.columns_ref(),
+                )?)
+            }
+            _ => None,
+        };
+        Ok(rows
+            .into_iter()
+            .map(|r| Row(r, self.settings.row.clone(), field_paths.clone()))
+            .collect::<Vec<Row>>()) // Wrap new type
     }
 
     // Modify result, so that page with unlimited page size can be loaded
@@ -71,21 +412,44 @@ where
             Page::Counted(start, records) => (start, records),
         };
         if page.is_counted() {
-            result.set_modifier("SQL_CALC_FOUND_ROWS".to_string());
+            match self.settings.page_count_strategy {
+                PageCountStrategy::FoundRows => {
+                    result.set_modifier("SQL_CALC_FOUND_ROWS".to_string())
+                }
+                PageCountStrategy::WindowCount => {
+                    // Window column comes first and is removed in `select_sql`
+                    result.set_modifier(WINDOW_COUNT_COLUMN.to_string());
+                }
+                PageCountStrategy::CountQuery => {}
+            }
         }
         result.set_extra(format!("LIMIT {}, {}", start, number_of_records));
     }
     // Load page and number of records without page limitation
-    async fn select_max_page_size_sql(&mut self, _sql: Sql) -> Result<u64> {
-        let sql = Sql("SELECT FOUND_ROWS()".to_string(), vec![]);
-        self.select_count_sql(sql).await
+    async fn select_max_page_size_sql(&mut self, sql: Sql) -> Result<u64> {
+        match self.settings.page_count_strategy {
+            PageCountStrategy::FoundRows => {
+                let sql = Sql("SELECT FOUND_ROWS()".to_string(), vec![]);
+                self.select_count_sql(sql).await
+            }
+            PageCountStrategy::WindowCount => match self.window_count.take() {
+                Some(count) => Ok(count),
+                None => self.select_count_sql(unlimited_count_sql(sql)).await,
+            },
+            PageCountStrategy::CountQuery => self.select_count_sql(unlimited_count_sql(sql)).await,
+        }
     }
     // Load single value
     async fn select_count_sql(&mut self, sql: Sql) -> Result<u64> {
         log_sql!(&sql);
         let Sql(sql_stmt, args) = sql;
-        let args = crate::sql_arg::values_from_ref(&args);
-        let row: Option<u64> = self.conn.exec_first(sql_stmt, args).await?;
+        let args = self.values(self.convert_args(&sql_stmt, args)?);
+        let statement_error = self.statement_error(&sql_stmt, &args);
+        let row: Option<u64> = self
+            .conn
+            .exec_first(sql_stmt.as_str(), args)
+            .await
+            .map_err(statement_error)?;
         Ok(row.unwrap_or(0))
         // Ok(row.into_iter().next().unwrap().unwrap().get(0).unwrap())
     }
@@ -93,33 +457,155 @@ where
     async fn execute_sql(&mut self, sql: Sql) -> Result<()> {
         log_mut_sql!(&sql);
         let Sql(sql_stmt, args) = sql;
-        let args = crate::sql_arg::values_from_ref(&args);
-        self.conn.exec_drop(sql_stmt, args).await?;
+        let args = self.values(self.convert_args(&sql_stmt, args)?);
+        let statement_error = self.statement_error(&sql_stmt, &args);
+        self.conn
+            .exec_drop(sql_stmt.as_str(), args)
+            .await
+            .map_err(statement_error)?;
         Ok(())
     }
     ///  Execute insert statement and return new keys
     async fn insert_sql(&mut self, sql: Sql) -> Result<Vec<SqlArg>> {
         log_mut_sql!(&sql);
         let Sql(sql_stmt, args) = sql;
-        let args = crate::sql_arg::values_from_ref(&args);
-        self.conn.exec_drop(sql_stmt, args).await?;
-        let row_count_sql = "SELECT ROW_COUNT()";
-        log_literal_sql!(&row_count_sql);
-        let affected_rows: Option<u64> = self.conn.query_first(row_count_sql).await?;
-        let last_insert_id = "SELECT LAST_INSERT_ID()";
-        log_literal_sql!(last_insert_id);
-        let last_insert_id: Option<u64> = self.conn.query_first(last_insert_id).await?;
-        let affected_rows = val!(affected_rows);
-        let start_id = val!(last_insert_id);
-        let mut ids: Vec<SqlArg> = Vec::with_capacity(affected_rows as usize);
-
-        // Create ids in descending order: greatest id first, smallest id last
-        // This allows draining the Vec when setting the ids on the entities
-        let mut id = start_id + affected_rows - 1;
-        for _i in 0..affected_rows {
-            ids.push(SqlArg::U64(id.into()));
-            id -= 1;
+        let args = self.convert_args(&sql_stmt, args)?;
+
+        let mut signed = false;
+        if let Some(statement) = InsertStatement::parse(&sql_stmt) {
+            let auto_increment_column = match self.auto_increment_column(&statement.table).await? {
+                // Keys in the column list are supplied by the caller
+                Some(c)
+                    if !statement
+                        .columns
+                        .iter()
+                        .any(|s| s.eq_ignore_ascii_case(&c.name)) =>
+                {
+                    c
+                }
+                _ => {
+                    // Nothing to read back
+                    let args = self.values(args);
+                    let statement_error = self.statement_error(&sql_stmt, &args);
+                    self.conn
+                        .exec_drop(sql_stmt.as_str(), args)
+                        .await
+                        .map_err(statement_error)?;
+                    return Ok(Vec::new());
+                }
+            };
+
+            // Multi row inserts without contiguous ids are split into single row inserts
+            let placeholders = statement
+                .rows
+                .iter()
+                .map(|r| count_placeholders(r))
+                .sum::<usize>()
+                + statement.tail_placeholders();
+            if statement.rows.len() > 1
+                && placeholders == args.len()
+                && !self.contiguous_ids().await?
+            {
+                return self
+                    .insert_rows(&statement, args, &auto_increment_column)
+                    .await;
+            }
+            signed = auto_increment_column.signed;
         }
-        Ok(ids)
+
+        let args = self.values(args);
+        let statement_error = self.statement_error(&sql_stmt, &args);
+        self.conn
+            .exec_drop(sql_stmt.as_str(), args)
+            .await
+            .map_err(statement_error)?;
+
+        // Affected rows and first generated id are taken from the OK packet
+        Ok(generated_ids(
+            self.conn.affected_rows(),
+            self.conn.last_insert_id(),
+            signed,
+        ))
     }
 }
+
+const WINDOW_COUNT_ALIAS: &str = "toql_page_count";
+const WINDOW_COUNT_COLUMN: &str = "COUNT(*) OVER() AS toql_page_count,";
+
+/// Returns true, if the select starts with the window count column of a counted page.
+fn has_window_count(sql: &str) -> bool {
+    let sql = sql.trim_start();
+    sql.get(.."SELECT".len())
+        .map_or(false, |s| s.eq_ignore_ascii_case("SELECT"))
+        && sql["SELECT".len()..]
+            .trim_start()
+            .starts_with(WINDOW_COUNT_COLUMN)
+}
+
+/// Toql field paths of the result columns of a select on the given mapper.
+///
+/// A column belongs to the field, whose filter Toql builds on the same alias and column.
+/// The field is searched with the column name on the root and on the paths,
+/// that canonical aliases like `payment_customer` carry.
+pub(crate) fn field_paths(
+    registry: &TableMapperRegistry,
+    roles: &HashSet<String>,
+    alias_format: &AliasFormat,
+    schema: &SchemaCache,
+    mapper: &str,
+    columns: &[mysql_async::Column],
+) -> Result<FieldPaths> {
+    let mut cache = schema.field_paths.lock().map_err(ToqlError::from)?;
+    Ok(columns
+        .iter()
+        .map(|c| {
+            let alias = c.table_str().to_string();
+            let column = match c.org_name_ref() {
+                n if n.is_empty() => c.name_str().to_string(),
+                _ => c.org_name_str().to_string(),
+            };
+            cache
+                .entry((mapper.to_string(), alias, column))
+                .or_insert_with_key(|(mapper, alias, column)| {
+                    let paths = alias.match_indices('_').map(|(i, _)| &alias[i + 1..]);
+                    std::iter::once(column.to_string())
+                        .chain(paths.map(|p| format!("{}_{}", p, column)))
+                        .find(|field| {
+                            let probe = probe(registry, roles, mapper, field, alias_format);
+                            matches!(probe, Ok(Some(Probe { filter: Some((q, c)), .. }))
+                                if q == *alias && c.eq_ignore_ascii_case(column))
+                        })
+                        .map(|field| format!("{}.{}", mapper, field))
+                })
+                .clone()
+        })
+        .collect())
+}
+
+/// Create ids in descending order: greatest id first, smallest id last.
+/// This allows draining the Vec when setting the ids on the entities.
+/// Ids of signed columns are returned as `SqlArg::I64`, so that they match the key type.
+fn generated_ids(affected_rows: u64, last_insert_id: Option<u64>, signed: bool) -> Vec<SqlArg> {
+    match last_insert_id {
+        Some(first_id) if first_id > 0 => (first_id..first_id + affected_rows)
+            .rev()
+            .map(|id| {
+                if signed {
+                    SqlArg::I64(id as i64)
+                } else {
+                    SqlArg::U64(id)
+                }
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Remove leading window count or cursor columns,
+/// so that deserialization starts at the first selected field.
+fn without_columns(row: mysql_async::Row, count: usize) -> mysql_async::Row {
+    let columns = row.columns();
+    let mut values = row.unwrap();
+    values.drain(..count.min(values.len()));
+    mysql_common::row::new_row(values, Arc::from(&columns[count.min(columns.len())..]))
+}
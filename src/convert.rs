@@ -0,0 +1,405 @@
+//! Converters for legacy column encodings.
+//!
+//! A [Converter] is registered for a field of a Toql mapper.
+//! It transforms the database value before the field is deserialized
+//! and the argument before it is bound to a statement.
+//! This allows to map legacy encodings without wrapper types in every entity.
+//!
+//! ```rust
+//! use toql_mysql_async::convert::{UnixTimestamp, YesNo};
+//!
+//! let mut toql = MySqlAsync::from(conn, &cache);
+//! toql.settings_mut()
+//!     .register_converter("User", "active", YesNo)
+//!     .register_converter("User", "created", UnixTimestamp);
+//! ```
+//!
+//! Filter arguments of a query are converted before Toql builds the statement.
+//! Values and inserted or updated arguments are matched by the column of the field,
+//! that Toql builds for the first statement of its mapper.
+//! Only fields of the root entity are converted in queries,
+//! joined entities are converted, when they are loaded or stored themselves.
+
+use crate::{
+    insert::{count_placeholders, unquote, InsertStatement},
+    paging::{keyword_positions, split_list},
+};
+use mysql_async::{Column, Value};
+use mysql_common::chrono::{NaiveDateTime, TimeZone, Utc};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{Arc, RwLock},
+};
+use toql::{
+    error::ToqlError,
+    prelude::{AliasFormat, Sql, SqlArg, TableMapperRegistry},
+    query::{field::Field, field_filter::FieldFilter, query_token::QueryToken, Query},
+    sql_builder::SqlBuilder,
+};
+
+/// Conversion between a legacy column encoding and the value, that the field expects.
+pub trait Converter: Send + Sync {
+    /// Convert a value from the database, before it is deserialized.
+    fn from_value(&self, value: Value) -> Value {
+        value
+    }
+    /// Convert an argument, before it is bound to a statement.
+    fn to_arg(&self, arg: SqlArg) -> SqlArg {
+        arg
+    }
+}
+
+/// Booleans stored as `'Y'` and `'N'`.
+#[derive(Debug, Clone, Copy)]
+pub struct YesNo;
+
+impl Converter for YesNo {
+    fn from_value(&self, value: Value) -> Value {
+        match &value {
+            Value::Bytes(b) if b.eq_ignore_ascii_case(b"Y") => Value::Int(1),
+            Value::Bytes(b) if b.eq_ignore_ascii_case(b"N") => Value::Int(0),
+            _ => value,
+        }
+    }
+    fn to_arg(&self, arg: SqlArg) -> SqlArg {
+        match arg {
+            SqlArg::Bool(true) => SqlArg::Str("Y".to_string()),
+            SqlArg::Bool(false) => SqlArg::Str("N".to_string()),
+            _ => arg,
+        }
+    }
+}
+
+/// Datetimes stored as seconds since the Unix epoch.
+#[derive(Debug, Clone, Copy)]
+pub struct UnixTimestamp;
+
+impl Converter for UnixTimestamp {
+    fn from_value(&self, value: Value) -> Value {
+        let seconds = match &value {
+            Value::Int(i) => Some(*i),
+            Value::UInt(u) => Some(*u as i64),
+            Value::Bytes(b) => std::str::from_utf8(b).ok().and_then(|s| s.parse().ok()),
+            _ => None,
+        };
+        match seconds.and_then(|s| Utc.timestamp_opt(s, 0).single()) {
+            Some(datetime) => Value::from(datetime.naive_utc()),
+            None => value,
+        }
+    }
+    fn to_arg(&self, arg: SqlArg) -> SqlArg {
+        match &arg {
+            SqlArg::Str(s) => NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+                .map(|d| SqlArg::I64(Utc.from_utc_datetime(&d).timestamp()))
+                .unwrap_or(arg),
+            _ => arg,
+        }
+    }
+}
+
+/// Registered converters keyed by mapper and field.
+#[derive(Clone, Default)]
+pub(crate) struct Converters {
+    fields: HashMap<(String, String), Arc<dyn Converter>>,
+    // Columns of the fields, shared by all clones of the settings
+    columns: Arc<RwLock<Columns>>,
+}
+
+/// Converters keyed by table and column, resolved once per mapper.
+#[derive(Default)]
+struct Columns {
+    mappers: HashSet<String>,
+    converters: HashMap<(String, String), Arc<dyn Converter>>,
+}
+
+impl fmt::Debug for Converters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.fields.keys()).finish()
+    }
+}
+
+impl Converters {
+    pub(crate) fn insert(&mut self, mapper: &str, field: &str, converter: Arc<dyn Converter>) {
+        self.fields
+            .insert((mapper.to_string(), field.to_string()), converter);
+        // Resolve again with the new field
+        self.columns = Arc::default();
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Resolve the columns of all registered fields, whose mapper is in the registry.
+    ///
+    /// The column of a field is taken from the filter, that Toql builds for it.
+    /// Mappers, that are not mapped yet, are resolved with a later statement.
+    pub(crate) fn resolve(&self, registry: &TableMapperRegistry, roles: &HashSet<String>) {
+        if self.fields.is_empty() {
+            return;
+        }
+        let pending = {
+            let columns = self.columns.read().unwrap_or_else(|e| e.into_inner());
+            self.fields
+                .keys()
+                .map(|(mapper, _)| mapper)
+                .filter(|m| !columns.mappers.contains(*m))
+                .collect::<HashSet<_>>()
+        };
+        for mapper in pending {
+            let mut converters = Vec::new();
+            let resolved = self
+                .fields
+                .iter()
+                .filter(|((m, _), _)| m == mapper)
+                .all(|((_, field), converter)| match field_column(registry, roles, mapper, field) {
+                    Ok(column) => {
+                        converters.extend(column.map(|c| (c, converter.clone())));
+                        true
+                    }
+                    Err(_) => false,
+                });
+            if resolved {
+                let mut columns = self.columns.write().unwrap_or_else(|e| e.into_inner());
+                columns.mappers.insert(mapper.clone());
+                for ((table, column), converter) in converters {
+                    columns.converters.insert(key(&table, &column), converter);
+                }
+            }
+        }
+    }
+
+    fn column(&self, table: &str, column: &str) -> Option<Arc<dyn Converter>> {
+        self.columns
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .converters
+            .get(&key(table, column))
+            .cloned()
+    }
+
+    /// Convert the value of a result column.
+    pub(crate) fn convert_value(&self, column: &Column, value: Value) -> Value {
+        if self.fields.is_empty() {
+            return value;
+        }
+        // Original names are empty for computed columns
+        let table = match column.org_table_ref() {
+            t if t.is_empty() => column.table_str(),
+            _ => column.org_table_str(),
+        };
+        let name = match column.org_name_ref() {
+            n if n.is_empty() => column.name_str(),
+            _ => column.org_name_str(),
+        };
+        match self.column(&table, &name) {
+            Some(converter) => converter.from_value(value),
+            None => value,
+        }
+    }
+
+    /// Query with the filter arguments of the registered fields converted
+    /// or `None`, if the query filters none of them.
+    pub(crate) fn convert_query<M>(&self, mapper: &str, query: &Query<M>) -> Option<Query<M>>
+    where
+        Query<M>: Clone,
+    {
+        let converter = |token: &QueryToken| match token {
+            QueryToken::Field(field) if field.filter.is_some() => self
+                .fields
+                .get(&(mapper.to_string(), field.name.to_string())),
+            _ => None,
+        };
+        if !query.tokens.iter().any(|t| converter(t).is_some()) {
+            return None;
+        }
+        let mut query = query.clone();
+        for token in query.tokens.iter_mut() {
+            if let Some(converter) = converter(token).cloned() {
+                if let QueryToken::Field(field) = token {
+                    field.filter = field.filter.take().map(|f| convert_filter(&*converter, f));
+                }
+            }
+        }
+        Some(query)
+    }
+
+    /// Convert the inserted values and assigned arguments of an insert or update statement.
+    ///
+    /// Filter arguments are converted with the query, see [Converters::convert_query].
+    pub(crate) fn convert_args(&self, sql_stmt: &str, mut args: Vec<SqlArg>) -> Vec<SqlArg> {
+        if self.fields.is_empty() {
+            return args;
+        }
+        for (i, table, column) in assigned_columns(sql_stmt) {
+            if let (Some(converter), Some(arg)) = (self.column(&table, &column), args.get_mut(i)) {
+                *arg = converter.to_arg(std::mem::replace(arg, SqlArg::Null));
+            }
+        }
+        args
+    }
+}
+
+fn key(table: &str, column: &str) -> (String, String) {
+    (table.to_ascii_lowercase(), column.to_ascii_lowercase())
+}
+
+fn convert_filter(converter: &dyn Converter, filter: FieldFilter) -> FieldFilter {
+    let arg = |a| converter.to_arg(a);
+    let args = |a: Vec<SqlArg>| a.into_iter().map(arg).collect();
+    match filter {
+        FieldFilter::Eq(a) => FieldFilter::Eq(arg(a)),
+        FieldFilter::Ne(a) => FieldFilter::Ne(arg(a)),
+        FieldFilter::Gt(a) => FieldFilter::Gt(arg(a)),
+        FieldFilter::Ge(a) => FieldFilter::Ge(arg(a)),
+        FieldFilter::Lt(a) => FieldFilter::Lt(arg(a)),
+        FieldFilter::Le(a) => FieldFilter::Le(arg(a)),
+        FieldFilter::Lk(a) => FieldFilter::Lk(arg(a)),
+        FieldFilter::Bw(a, b) => FieldFilter::Bw(arg(a), arg(b)),
+        FieldFilter::In(a) => FieldFilter::In(args(a)),
+        FieldFilter::Out(a) => FieldFilter::Out(args(a)),
+        // Custom filters interpret their arguments themselves
+        f => f,
+    }
+}
+
+/// Table and column of a field, taken from the filter `alias.column = ?`,
+/// that Toql builds for it, or `None` for a computed field.
+fn field_column(
+    registry: &TableMapperRegistry,
+    roles: &HashSet<String>,
+    mapper: &str,
+    field: &str,
+) -> std::result::Result<Option<(String, String)>, ToqlError> {
+    let probe = probe(registry, roles, mapper, field, &AliasFormat::Canonical)?;
+    Ok(probe.and_then(|p| match p.filter {
+        Some((qualifier, column)) if qualifier == p.alias => Some((p.table, column)),
+        _ => None,
+    }))
+}
+
+/// Select, that Toql builds for the query `field eq 0`.
+pub(crate) struct Probe {
+    /// Table after `FROM`
+    pub(crate) table: String,
+    /// Alias of the table after `FROM`
+    pub(crate) alias: String,
+    /// Qualifier and column of the filter `qualifier.column = ?`, `None` for an expression
+    pub(crate) filter: Option<(String, String)>,
+}
+
+/// Build the select for the query `field eq 0` on the given mapper.
+pub(crate) fn probe(
+    registry: &TableMapperRegistry,
+    roles: &HashSet<String>,
+    mapper: &str,
+    field: &str,
+    alias_format: &AliasFormat,
+) -> std::result::Result<Option<Probe>, ToqlError> {
+    let query = Query::<()>::new().and(Field::from(field).eq(0));
+    let mut builder = SqlBuilder::new(mapper, registry).with_roles(roles.clone());
+    let Sql(stmt, _) = builder.build_select("", &query)?.to_sql(alias_format);
+    Ok(parse_probe(&stmt))
+}
+
+/// Parse `SELECT .. FROM table alias .. WHERE qualifier.column = ?`.
+fn parse_probe(stmt: &str) -> Option<Probe> {
+    let from = *keyword_positions(stmt, "FROM").first()? + "FROM".len();
+    let mut words = stmt[from..].split_whitespace();
+    let table = unquote(words.next()?);
+    let alias = unquote(words.next()?);
+    let filter = keyword_positions(stmt, "WHERE").last().and_then(|&pos| {
+        let filter = stmt[pos + "WHERE".len()..]
+            .trim()
+            .trim_start_matches('(')
+            .trim_end_matches(')');
+        let lhs = filter.trim().strip_suffix('?')?.trim_end().strip_suffix('=')?;
+        let (qualifier, column) = lhs.trim().split_once('.')?;
+        let column = unquote(column);
+        if is_name(&column) {
+            Some((unquote(qualifier), column))
+        } else {
+            None
+        }
+    });
+    Some(Probe {
+        table,
+        alias,
+        filter,
+    })
+}
+
+/// Column of `alias.column`, if it is qualified with the given alias.
+fn qualified_column(expr: &str, alias: &str) -> Option<String> {
+    let (qualifier, column) = expr.trim().split_once('.')?;
+    let column = unquote(column);
+    if unquote(qualifier) == alias && is_name(&column) {
+        Some(column)
+    } else {
+        None
+    }
+}
+
+/// Returns true for a plain column name.
+fn is_name(column: &str) -> bool {
+    !column.is_empty()
+        && column
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Argument positions with the table and column, they are inserted into or assigned to.
+///
+/// Toql inserts with `INSERT INTO table (a, b) VALUES (?, ?)`
+/// and updates with `UPDATE table alias SET alias.a = ?, alias.b = ? WHERE ..`.
+/// Placeholders within expressions are skipped.
+fn assigned_columns(sql_stmt: &str) -> Vec<(usize, String, String)> {
+    let mut columns = Vec::new();
+    if let Some(insert) = InsertStatement::parse(sql_stmt) {
+        let mut i = 0;
+        for row in &insert.rows {
+            let values = row.trim_start_matches('(').trim_end_matches(')');
+            for (value, column) in split_list(values).into_iter().zip(&insert.columns) {
+                if value == "?" {
+                    columns.push((i, insert.table.clone(), column.clone()));
+                }
+                i += count_placeholders(value);
+            }
+        }
+        return columns;
+    }
+
+    let update = match keyword_positions(sql_stmt, "UPDATE").first() {
+        Some(&pos) if sql_stmt[..pos].trim().is_empty() => pos + "UPDATE".len(),
+        _ => return columns,
+    };
+    let set = match keyword_positions(sql_stmt, "SET").first() {
+        Some(&pos) => pos,
+        None => return columns,
+    };
+    let mut words = sql_stmt[update..set].split_whitespace();
+    let table = match words.next() {
+        Some(table) => unquote(table),
+        None => return columns,
+    };
+    let alias = words.next().map(unquote).unwrap_or_else(|| table.clone());
+    let end = keyword_positions(sql_stmt, "WHERE")
+        .into_iter()
+        .find(|&pos| pos > set)
+        .unwrap_or(sql_stmt.len());
+    let mut i = count_placeholders(&sql_stmt[..set]);
+    for assignment in split_list(&sql_stmt[set + "SET".len()..end]) {
+        if let Some((lhs, rhs)) = assignment.split_once('=') {
+            if rhs.trim() == "?" {
+                if let Some(column) = qualified_column(lhs, &alias)
+                    .or_else(|| Some(unquote(lhs.trim())).filter(|c| !c.contains('.')))
+                {
+                    columns.push((i, table.clone(), column));
+                }
+            }
+        }
+        i += count_placeholders(assignment);
+    }
+    columns
+}
@@ -1,18 +1,338 @@
 //! The error type.
-use mysql_async::{Error, FromValueError};
+use mysql_async::{Error, FromValueError, ServerError, Value};
+use std::time::Duration;
 use toql::{error::ToqlError, sql_builder::sql_builder_error::SqlBuilderError};
 use thiserror::Error;
 
 /// An error type that combines all possible errors by this library.
 #[derive(Error, Debug)]
-#[error("{0}")]
 pub enum ToqlMySqlAsyncError {
     /// Error from Toql
+    #[error("{0}")]
     ToqlError(#[from] ToqlError),
-    /// Database error from the MySQL
-    MySqlError(#[from] Error),
+    /// Database error from the MySQL, that is not classified below
+    #[error("{}", with_context(.error, .context))]
+    MySqlError {
+        #[source]
+        error: Error,
+        /// Statement and entity, see [ToqlMySqlAsyncError::context]
+        context: Option<Box<ErrorContext>>,
+    },
+    /// Insert or update violates a unique index (1062)
+    #[error("{}", with_context(.error, .context))]
+    DuplicateKeyError {
+        index: String,
+        #[source]
+        error: ServerError,
+        /// Statement and entity, see [ToqlMySqlAsyncError::context]
+        context: Option<Box<ErrorContext>>,
+    },
+    /// Insert, update or delete violates a foreign key constraint (1451, 1452)
+    #[error("{}", with_context(.error, .context))]
+    ForeignKeyError {
+        constraint: String,
+        #[source]
+        error: ServerError,
+        context: Option<Box<ErrorContext>>,
+    },
+    /// Transaction was rolled back to resolve a deadlock (1213)
+    #[error("{}", with_context(.error, .context))]
+    DeadlockError {
+        #[source]
+        error: ServerError,
+        context: Option<Box<ErrorContext>>,
+    },
+    /// Lock could not be acquired within `innodb_lock_wait_timeout` (1205)
+    #[error("{}", with_context(.error, .context))]
+    LockWaitTimeoutError {
+        #[source]
+        error: ServerError,
+        context: Option<Box<ErrorContext>>,
+    },
+    /// Value is too long for its column in strict SQL mode (1406)
+    #[error("{}", with_context(.error, .context))]
+    DataTooLongError {
+        column: String,
+        #[source]
+        error: ServerError,
+        context: Option<Box<ErrorContext>>,
+    },
     /// Deserialization error from the MySQL
+    #[error("{0}")]
     FromValueError(#[from] FromValueError),
+    /// Column can't be deserialized into the type of its field
+    #[error("{field}: expected {expected}, got {found}")]
+    DeserializeError {
+        /// Position of the column in the row
+        index: usize,
+        column: String,
+        /// Toql field path, e.g. `Payment.account_name`, or table and column, if the column isn't mapped
+        field: String,
+        /// Rust type of the field
+        expected: String,
+        /// SQL type of the column, e.g. `INT`
+        found: String,
+    },
+    /// Number doesn't fit into its field in [NumericMode::Strict](crate::row::NumericMode::Strict)
+    #[error("{field}: value `{value}` doesn't fit into {expected}")]
+    NumericError {
+        field: String,
+        value: String,
+        expected: String,
+    },
+    /// Value can't be serialized into a JSON argument
+    #[cfg(feature = "json")]
+    #[error("unable to serialize JSON argument: {0}")]
+    JsonError(#[from] serde_json::Error),
+    /// Row has no column with the given name or index
+    #[error("no column `{0}` in row")]
+    ColumnNotFoundError(String),
+    /// No connection could be taken from the pool within the acquisition timeout
+    #[error("no pooled connection available within {0:?}")]
+    PoolTimeout(Duration),
+    /// Merge paths can't be loaded as stream
+    #[error("merge path `{0}` can't be loaded as stream")]
+    StreamMergeError(String),
+    /// Keyset cursor can't be parsed
+    #[error("invalid keyset cursor `{0}`")]
+    CursorError(String),
+    /// Value of an `ENUM` or `SET` column has no matching Rust value
+    #[error("unknown value `{value}` in column `{column}`")]
+    EnumValueError { column: String, value: String },
+    /// Zero date or invalid date, that is rejected by the [ZeroDatePolicy](crate::row::ZeroDatePolicy)
+    #[error("invalid date `{value}` in field `{field}` of row with key `{key}`")]
+    ZeroDateError {
+        field: String,
+        key: String,
+        value: String,
+    },
+    /// Rollback to a savepoint failed after the nested scope returned an error
+    #[error("{error} (rollback failed: {rollback})")]
+    RollbackError {
+        /// Error of the nested scope
+        #[source]
+        error: Box<ToqlMySqlAsyncError>,
+        /// Error of the rollback
+        rollback: Box<ToqlMySqlAsyncError>,
+    },
+}
+
+/// Statement and entity of a failed Toql call.
+#[derive(Debug, Default)]
+pub struct ErrorContext {
+    operation: Option<&'static str>,
+    entity: Option<String>,
+    statement: Option<String>,
+    args: Option<Vec<String>>,
+}
+
+impl ErrorContext {
+    /// Operation of the Toql call, e.g. `insert into`.
+    pub fn operation(&self) -> Option<&str> {
+        self.operation
+    }
+    /// Type name of the entity.
+    pub fn entity(&self) -> Option<&str> {
+        self.entity.as_deref()
+    }
+    /// Text of the failing statement.
+    pub fn statement(&self) -> Option<&str> {
+        self.statement.as_deref()
+    }
+    /// Arguments of the failing statement, if enabled with [ErrorArgs].
+    pub fn args(&self) -> Option<&[String]> {
+        self.args.as_deref()
+    }
+}
+
+/// Message of a database error, prefixed with operation and entity, if known.
+fn with_context(error: &dyn std::fmt::Display, context: &Option<Box<ErrorContext>>) -> String {
+    match context.as_deref() {
+        Some(ErrorContext {
+            operation: Some(operation),
+            entity: Some(entity),
+            ..
+        }) => format!("{} {} failed: {}", operation, entity, error),
+        _ => error.to_string(),
+    }
+}
+
+/// Arguments of a failing statement, that are kept in the [ErrorContext].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorArgs {
+    /// Keep no arguments
+    Omit,
+    /// Keep only the types of the arguments, e.g. `Bytes(12)` for a string with 12 bytes
+    Redacted,
+    /// Keep the arguments as SQL literals
+    Plain,
+}
+
+impl Default for ErrorArgs {
+    fn default() -> Self {
+        ErrorArgs::Omit
+    }
+}
+
+impl ErrorArgs {
+    /// Arguments as kept in the error context.
+    pub(crate) fn record(&self, args: &[Value]) -> Option<Vec<String>> {
+        match self {
+            ErrorArgs::Omit => None,
+            ErrorArgs::Redacted => Some(
+                args.iter()
+                    .map(|a| match a {
+                        Value::NULL => "NULL".to_string(),
+                        Value::Bytes(b) => format!("Bytes({})", b.len()),
+                        Value::Int(_) => "Int".to_string(),
+                        Value::UInt(_) => "UInt".to_string(),
+                        Value::Float(_) => "Float".to_string(),
+                        Value::Double(_) => "Double".to_string(),
+                        Value::Date(..) => "Date".to_string(),
+                        Value::Time(..) => "Time".to_string(),
+                    })
+                    .collect(),
+            ),
+            ErrorArgs::Plain => Some(args.iter().map(|a| a.as_sql(false)).collect()),
+        }
+    }
+}
+
+impl ToqlMySqlAsyncError {
+    /// Error of a statement with its text and arguments.
+    pub(crate) fn statement(err: Error, statement: &str, args: Option<Vec<String>>) -> Self {
+        let mut err = ToqlMySqlAsyncError::from(err);
+        if let Some(context) = err.context_mut() {
+            let context = context.get_or_insert_with(Box::default);
+            context.statement = Some(statement.to_string());
+            context.args = args;
+        }
+        err
+    }
+
+    /// Add operation and entity to the context of a database error.
+    pub(crate) fn with_entity(mut self, operation: &'static str, entity: String) -> Self {
+        if let Some(context) = self.context_mut() {
+            let context = context.get_or_insert_with(Box::default);
+            context.operation.get_or_insert(operation);
+            context.entity.get_or_insert(entity);
+        }
+        self
+    }
+
+    fn context_mut(&mut self) -> Option<&mut Option<Box<ErrorContext>>> {
+        match self {
+            ToqlMySqlAsyncError::MySqlError { context, .. }
+            | ToqlMySqlAsyncError::DuplicateKeyError { context, .. }
+            | ToqlMySqlAsyncError::ForeignKeyError { context, .. }
+            | ToqlMySqlAsyncError::DeadlockError { context, .. }
+            | ToqlMySqlAsyncError::LockWaitTimeoutError { context, .. }
+            | ToqlMySqlAsyncError::DataTooLongError { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Statement and entity of a database error, if known.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            ToqlMySqlAsyncError::MySqlError { context, .. }
+            | ToqlMySqlAsyncError::DuplicateKeyError { context, .. }
+            | ToqlMySqlAsyncError::ForeignKeyError { context, .. }
+            | ToqlMySqlAsyncError::DeadlockError { context, .. }
+            | ToqlMySqlAsyncError::LockWaitTimeoutError { context, .. }
+            | ToqlMySqlAsyncError::DataTooLongError { context, .. } => context.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns true, if the failed transaction can be run again,
+    /// e.g. after a deadlock or a lock wait timeout.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ToqlMySqlAsyncError::DeadlockError { .. }
+                | ToqlMySqlAsyncError::LockWaitTimeoutError { .. }
+        )
+    }
+
+    /// Returns true, if the statement conflicts with existing data,
+    /// e.g. a duplicate key or a foreign key violation.
+    pub fn is_conflict(&self) -> bool {
+        matches!(
+            self,
+            ToqlMySqlAsyncError::DuplicateKeyError { .. }
+                | ToqlMySqlAsyncError::ForeignKeyError { .. }
+        )
+    }
+
+    /// Error code of the server or `None`, if the error did not come from the server.
+    pub fn server_code(&self) -> Option<u16> {
+        match self {
+            ToqlMySqlAsyncError::MySqlError {
+                error: Error::Server(e),
+                ..
+            }
+            | ToqlMySqlAsyncError::DuplicateKeyError { error: e, .. }
+            | ToqlMySqlAsyncError::ForeignKeyError { error: e, .. }
+            | ToqlMySqlAsyncError::DeadlockError { error: e, .. }
+            | ToqlMySqlAsyncError::LockWaitTimeoutError { error: e, .. }
+            | ToqlMySqlAsyncError::DataTooLongError { error: e, .. } => Some(e.code),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for ToqlMySqlAsyncError {
+    fn from(err: Error) -> ToqlMySqlAsyncError {
+        let error = match err {
+            Error::Server(error) => error,
+            error => {
+                return ToqlMySqlAsyncError::MySqlError {
+                    error,
+                    context: None,
+                }
+            }
+        };
+        match error.code {
+            // Duplicate entry '1' for key 'PRIMARY'
+            1062 => ToqlMySqlAsyncError::DuplicateKeyError {
+                index: quoted_after(&error.message, "for key '", '\'').unwrap_or_default(),
+                error,
+                context: None,
+            },
+            // ... a foreign key constraint fails (`db`.`child`, CONSTRAINT `fk` FOREIGN KEY ...)
+            1451 | 1452 => ToqlMySqlAsyncError::ForeignKeyError {
+                constraint: quoted_after(&error.message, "CONSTRAINT `", '`').unwrap_or_default(),
+                error,
+                context: None,
+            },
+            1213 => ToqlMySqlAsyncError::DeadlockError {
+                error,
+                context: None,
+            },
+            1205 => ToqlMySqlAsyncError::LockWaitTimeoutError {
+                error,
+                context: None,
+            },
+            // Data too long for column 'name' at row 1
+            1406 => ToqlMySqlAsyncError::DataTooLongError {
+                column: quoted_after(&error.message, "for column '", '\'').unwrap_or_default(),
+                error,
+                context: None,
+            },
+            _ => ToqlMySqlAsyncError::MySqlError {
+                error: Error::Server(error),
+                context: None,
+            },
+        }
+    }
+}
+
+/// Text between `prefix` and the next `quote`.
+fn quoted_after(message: &str, prefix: &str, quote: char) -> Option<String> {
+    let start = message.find(prefix)? + prefix.len();
+    let end = start + message[start..].find(quote)?;
+    Some(message[start..end].to_string())
 }
 
 impl From<SqlBuilderError> for ToqlMySqlAsyncError {
@@ -20,4 +340,3 @@ impl From<SqlBuilderError> for ToqlMySqlAsyncError {
         ToqlMySqlAsyncError::ToqlError(err.into())
     }
 }
-
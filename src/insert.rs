@@ -0,0 +1,144 @@
+//! Parser for the insert statements, that Toql builds.
+//!
+//! The backend needs the table, the column list and the single value rows
+//! to decide how to read back the generated ids.
+
+/// Insert statement of the form `INSERT INTO table (a, b) VALUES (?, ?), (?, ?) tail`.
+#[derive(Debug)]
+pub(crate) struct InsertStatement<'a> {
+    pub(crate) table: String,
+    pub(crate) columns: Vec<String>,
+    head: &'a str,
+    pub(crate) rows: Vec<&'a str>,
+    tail: &'a str,
+}
+
+impl<'a> InsertStatement<'a> {
+    /// Parse insert statement or return `None` for any other statement.
+    pub(crate) fn parse(stmt: &'a str) -> Option<Self> {
+        let upper = stmt.to_ascii_uppercase();
+        if !upper.trim_start().starts_with("INSERT") {
+            return None;
+        }
+        let table_start = upper.find(" INTO ")? + " INTO ".len();
+        let table_start = table_start + (stmt[table_start..].len() - stmt[table_start..].trim_start().len());
+        let table_end = table_start
+            + stmt[table_start..].find(|c: char| c.is_whitespace() || c == '(')?;
+        let table = unquote(&stmt[table_start..table_end]);
+
+        let columns_start = table_end + stmt[table_end..].find('(')?;
+        let columns_end = columns_start + group_end(&stmt[columns_start..])?;
+        let columns = stmt[columns_start + 1..columns_end - 1]
+            .split(',')
+            .map(|c| unquote(c.trim()))
+            .collect::<Vec<_>>();
+
+        // Rows must follow the columns, `INSERT .. SELECT` may contain `VALUES(col)` in its tail
+        let values = columns_end + (upper[columns_end..].len() - upper[columns_end..].trim_start().len());
+        if !upper[values..].starts_with("VALUES") {
+            return None;
+        }
+        let head = stmt[..values].trim_end();
+
+        let mut rows = Vec::new();
+        let mut pos = values + "VALUES".len();
+        loop {
+            let rest = &stmt[pos..];
+            let skipped = rest.len() - rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',').len();
+            if !stmt[pos + skipped..].starts_with('(') {
+                break;
+            }
+            let start = pos + skipped;
+            let end = start + group_end(&stmt[start..])?;
+            rows.push(&stmt[start..end]);
+            pos = end;
+        }
+        if rows.is_empty() {
+            return None;
+        }
+
+        Some(InsertStatement {
+            table,
+            columns,
+            head,
+            rows,
+            tail: &stmt[pos..],
+        })
+    }
+
+    /// Statement, that inserts only the given row.
+    pub(crate) fn row_sql(&self, row: &str) -> String {
+        format!("{} VALUES {}{}", self.head, row, self.tail)
+    }
+
+    /// Number of placeholders after the rows, e.g. in `ON DUPLICATE KEY UPDATE`.
+    pub(crate) fn tail_placeholders(&self) -> usize {
+        count_placeholders(self.tail)
+    }
+}
+
+/// Count `?` placeholders outside of quotes.
+pub(crate) fn count_placeholders(sql: &str) -> usize {
+    let mut count = 0;
+    scan(sql, |_, c, depth| {
+        if c == '?' {
+            count += 1;
+        }
+        depth >= 0
+    });
+    count
+}
+
+/// Byte position after the parenthesized group, that starts the given sql.
+fn group_end(sql: &str) -> Option<usize> {
+    let mut end = None;
+    scan(sql, |i, c, depth| {
+        if c == ')' && depth == 0 {
+            end = Some(i + 1);
+            return false;
+        }
+        true
+    });
+    end
+}
+
+/// Walk over all chars outside of quotes with their parenthesis depth
+/// until the callback returns `false`.
+pub(crate) fn scan<F>(sql: &str, mut f: F)
+where
+    F: FnMut(usize, char, i32) -> bool,
+{
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in sql.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '\'' | '"' | '`' => quote = Some(c),
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if !f(i, c, depth) {
+            break;
+        }
+    }
+}
+
+/// Identifier without backticks, e.g. `db`.`table` becomes db.table.
+pub(crate) fn unquote(identifier: &str) -> String {
+    identifier
+        .split('.')
+        .map(|p| p.trim_matches('`'))
+        .collect::<Vec<_>>()
+        .join(".")
+}
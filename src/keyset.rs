@@ -0,0 +1,288 @@
+//! Keyset pagination.
+//!
+//! Instead of skipping records with an offset, a keyset page seeks past the position of the
+//! last entity from the previous page. Deep pages are as fast as the first one.
+//! The position is given by the ordering of the query, the keys of the entity break ties.
+//!
+//! ```rust
+//! use toql_mysql_async::paging::Page;
+//!
+//! let query = query!(Payment, "-amount, *");
+//! let (payments, info) = toql.load_paged(&query, Page::Keyset(None, 50)).await?;
+//! if let Some(cursor) = info.next {
+//!     let (next_payments, _) = toql.load_paged(&query, Page::Keyset(Some(cursor), 50)).await?;
+//! }
+//! ```
+use crate::{
+    error::ToqlMySqlAsyncError,
+    insert::count_placeholders,
+    paging::{keyword_positions, split_list},
+    result::Result,
+};
+use mysql_async::Value;
+use std::{fmt, str::FromStr};
+use toql::prelude::{Sql, SqlArg};
+
+/// Prefix of the columns, that carry the cursor values.
+const CURSOR_ALIAS: &str = "toql_cursor_";
+
+/// Position after the last entity of a keyset page.
+///
+/// The cursor holds the values of the order expressions of the last entity.
+/// It is opaque, to pass it to a client, convert it into a string
+/// and parse it back with [FromStr].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor(Vec<SqlArg>);
+
+/// Order expression of a keyset select.
+struct OrderExpression {
+    sql: String,
+    args: Vec<SqlArg>,
+    descending: bool,
+}
+
+/// Rewrite the select of a keyset page.
+///
+/// The expressions of the `ORDER BY` clause are selected as leading cursor columns.
+/// If the page starts after a cursor, they are compared with its values:
+/// `(a, b) > (x, y)` becomes `(a > x) OR (a = x AND b > y)`, descending expressions use `<`.
+/// Returns the statement and the number of cursor columns.
+pub(crate) fn keyset_sql(sql: Sql, after: Option<&Cursor>) -> Result<(Sql, usize)> {
+    let Sql(stmt, mut args) = sql;
+    let order = match keyword_positions(&stmt, "ORDER").last() {
+        Some(&order) => order,
+        None => return Ok((Sql(stmt, args), 0)),
+    };
+    let order_end = keyword_positions(&stmt, "LIMIT")
+        .last()
+        .copied()
+        .filter(|&limit| limit > order)
+        .unwrap_or_else(|| stmt.len());
+    let list_start = order
+        + "ORDER".len()
+        + keyword_positions(&stmt[order + "ORDER".len()..order_end], "BY")
+            .first()
+            .map_or(0, |by| by + "BY".len());
+
+    let mut expressions: Vec<OrderExpression> = Vec::new();
+    let mut arg_index = count_placeholders(&stmt[..list_start]);
+    for item in split_list(&stmt[list_start..order_end]) {
+        let placeholders = count_placeholders(item);
+        let item_args = args
+            .get(arg_index..arg_index + placeholders)
+            .map(|a| a.to_vec())
+            .unwrap_or_default();
+        arg_index += placeholders;
+        let (sql, descending) = direction(item);
+        // Duplicate expressions don't change the position
+        if !expressions.iter().any(|e| e.sql.eq_ignore_ascii_case(sql)) {
+            expressions.push(OrderExpression {
+                sql: sql.to_string(),
+                args: item_args,
+                descending,
+            });
+        }
+    }
+
+    let mut stmt = stmt;
+    if let Some(cursor) = after {
+        let Cursor(values) = cursor;
+        if values.len() != expressions.len() {
+            return Err(ToqlMySqlAsyncError::CursorError(cursor.to_string()));
+        }
+        let mut terms = Vec::with_capacity(expressions.len());
+        let mut seek_args = Vec::new();
+        for (i, expression) in expressions.iter().enumerate() {
+            let mut term = Vec::with_capacity(i + 1);
+            for (previous, value) in expressions[..i].iter().zip(values) {
+                term.push(format!("{} = ?", previous.sql));
+                seek_args.extend(previous.args.iter().cloned());
+                seek_args.push(value.to_owned());
+            }
+            let op = if expression.descending { "<" } else { ">" };
+            term.push(format!("{} {} ?", expression.sql, op));
+            seek_args.extend(expression.args.iter().cloned());
+            seek_args.push(values[i].to_owned());
+            terms.push(format!("({})", term.join(" AND ")));
+        }
+        let seek = terms.join(" OR ");
+
+        // Seek predicate goes at the end of the where clause
+        let clause_end = ["GROUP", "HAVING", "ORDER"]
+            .iter()
+            .filter_map(|k| keyword_positions(&stmt, k).first().copied())
+            .min()
+            .unwrap_or(order);
+        let args_index = count_placeholders(&stmt[..clause_end]);
+        stmt = match keyword_positions(&stmt[..clause_end], "WHERE").first() {
+            Some(&w) => {
+                let body_start = w + "WHERE".len();
+                format!(
+                    "{} ({}) AND ({}) {}",
+                    &stmt[..body_start],
+                    stmt[body_start..clause_end].trim(),
+                    seek,
+                    &stmt[clause_end..]
+                )
+            }
+            None => format!(
+                "{} WHERE ({}) {}",
+                stmt[..clause_end].trim_end(),
+                seek,
+                &stmt[clause_end..]
+            ),
+        };
+        args.splice(args_index..args_index, seek_args);
+    }
+
+    // Cursor columns come first and are removed in `select_sql`
+    let mut columns_start = match keyword_positions(&stmt, "SELECT").first() {
+        Some(&select) => select + "SELECT".len(),
+        None => return Ok((Sql(stmt, args), 0)),
+    };
+    if let Some(&distinct) = keyword_positions(&stmt[columns_start..], "DISTINCT").first() {
+        if stmt[columns_start..columns_start + distinct].trim().is_empty() {
+            columns_start += distinct + "DISTINCT".len();
+        }
+    }
+    let columns = expressions
+        .iter()
+        .enumerate()
+        .map(|(i, e)| format!("{} AS {}{}", e.sql, CURSOR_ALIAS, i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let args_index = count_placeholders(&stmt[..columns_start]);
+    args.splice(
+        args_index..args_index,
+        expressions.iter().flat_map(|e| e.args.iter().cloned()),
+    );
+    let stmt = format!(
+        "{} {},{}",
+        &stmt[..columns_start],
+        columns,
+        &stmt[columns_start..]
+    );
+
+    Ok((Sql(stmt, args), expressions.len()))
+}
+
+/// Order expression without `ASC` or `DESC` and true, if it's descending.
+fn direction(item: &str) -> (&str, bool) {
+    let ends_with = |suffix: &str| {
+        item.len() >= suffix.len()
+            && item
+                .get(item.len() - suffix.len()..)
+                .map_or(false, |s| s.eq_ignore_ascii_case(suffix))
+    };
+    if ends_with(" DESC") {
+        (item[..item.len() - " DESC".len()].trim_end(), true)
+    } else if ends_with(" ASC") {
+        (item[..item.len() - " ASC".len()].trim_end(), false)
+    } else {
+        (item, false)
+    }
+}
+
+/// Cursor with the values of the leading cursor columns of a row.
+pub(crate) fn row_cursor(row: &mysql_async::Row, columns: usize) -> Result<Cursor> {
+    let mut values = Vec::with_capacity(columns);
+    for i in 0..columns {
+        let name = format!("{}{}", CURSOR_ALIAS, i);
+        match row.columns_ref().get(i) {
+            Some(column) if column.name_str() == name.as_str() => {}
+            _ => return Err(ToqlMySqlAsyncError::ColumnNotFoundError(name)),
+        }
+        values.push(cursor_arg(row.as_ref(i).cloned().unwrap_or(Value::NULL)));
+    }
+    Ok(Cursor(values))
+}
+
+/// Argument to compare a column with the value of a cursor column.
+fn cursor_arg(value: Value) -> SqlArg {
+    match value {
+        Value::NULL => SqlArg::Null,
+        Value::Int(v) => SqlArg::I64(v),
+        Value::UInt(v) => SqlArg::U64(v),
+        Value::Float(v) => SqlArg::F64(v as f64),
+        Value::Double(v) => SqlArg::F64(v),
+        Value::Bytes(v) => SqlArg::Bytes(v),
+        Value::Date(year, month, day, hour, minute, second, micros) => SqlArg::Str(format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}",
+            year, month, day, hour, minute, second, micros
+        )),
+        Value::Time(negative, days, hours, minutes, seconds, micros) => SqlArg::Str(format!(
+            "{}{:02}:{:02}:{:02}.{:06}",
+            if negative { "-" } else { "" },
+            days * 24 + hours as u32,
+            minutes,
+            seconds,
+            micros
+        )),
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = self
+            .0
+            .iter()
+            .map(|a| match a {
+                SqlArg::U64(v) => format!("u{}", v),
+                SqlArg::I64(v) => format!("i{}", v),
+                SqlArg::F64(v) => format!("f{}", v),
+                SqlArg::Str(v) => format!("s{}", v.replace('\\', "\\\\").replace(',', "\\,")),
+                SqlArg::Bytes(v) => format!(
+                    "x{}",
+                    v.iter().map(|b| format!("{:02x}", b)).collect::<String>()
+                ),
+                SqlArg::Bool(v) => format!("b{}", v),
+                SqlArg::Null => "n".to_string(),
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", parts.join(","))
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = ToqlMySqlAsyncError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || ToqlMySqlAsyncError::CursorError(s.to_string());
+
+        // Split on unescaped commas
+        let mut parts = Vec::new();
+        let mut part = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => part.push(chars.next().ok_or_else(invalid)?),
+                ',' => parts.push(std::mem::take(&mut part)),
+                _ => part.push(c),
+            }
+        }
+        parts.push(part);
+
+        let values = parts
+            .into_iter()
+            .map(|p| {
+                let (tag, value) = p.split_at(p.chars().next().map(char::len_utf8).unwrap_or(0));
+                match tag {
+                    "u" => value.parse().map(SqlArg::U64).ok(),
+                    "i" => value.parse().map(SqlArg::I64).ok(),
+                    "f" => value.parse().map(SqlArg::F64).ok(),
+                    "s" => Some(SqlArg::Str(value.to_string())),
+                    "x" if value.len() % 2 == 0 => (0..value.len())
+                        .step_by(2)
+                        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+                        .collect::<Option<Vec<_>>>()
+                        .map(SqlArg::Bytes),
+                    "b" => value.parse().map(SqlArg::Bool).ok(),
+                    "n" => Some(SqlArg::Null),
+                    _ => None,
+                }
+                .ok_or_else(invalid)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Cursor(values))
+    }
+}
@@ -33,6 +33,22 @@
 //! let mut toql = MySqlAsync::from(tx, &cache);
 //! ```
 //!
+//! Or run a closure in a transaction, that is committed on `Ok` and rolled back on `Err`:
+//! ```rust
+//! let mut toql = MySqlAsync::from(conn, &cache);
+//! toql.transaction(TxOpts::default(), |tx| Box::pin(async move {
+//!     tx.insert_many(&mut payments, paths!(top)).await
+//! })).await?;
+//! ```
+//!
+//! Web handlers can also keep a pool wrapper, that takes a connection for every call:
+//! ```rust
+//! use toql_mysql_async::prelude::MySqlAsyncPool;
+//!
+//! let mut toql = MySqlAsyncPool::from(pool, &cache);
+//! let users = toql.load_many(query!(User, "*")).await?;
+//! ```
+//!
 //! ## License
 //! Toql MySqlAsync is distributed under the terms of both the MIT license and the
 //! Apache License (Version 2.0).
@@ -42,7 +58,7 @@ use std::{
     sync::RwLockReadGuard,
 };
 use toql::{error::ToqlError, alias_format::AliasFormat, prelude::{Cache, Context, SqlArg}, table_mapper_registry::TableMapperRegistry};
-use crate::{queryable::Queryable, backend::MySqlAsyncBackend};
+use crate::{queryable::Queryable, backend::MySqlAsyncBackend, settings::Settings};
 
 // Reexport for derive produced code
 pub use mysql_async;
@@ -58,6 +74,19 @@ pub mod backend;
 pub mod prelude;
 pub mod toql_api;
 pub mod queryable;
+pub mod pool;
+pub mod transaction;
+pub mod stream;
+pub mod paging;
+pub mod keyset;
+pub mod convert;
+pub mod retry;
+pub mod settings;
+mod insert;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+#[cfg(any(test, feature = "test-server"))]
+pub mod test_server;
 
 #[cfg(test)]
 mod test;
@@ -90,15 +119,29 @@ where
     }
 
     pub fn with_context(conn: C, cache: &'a Cache, context: Context) -> MySqlAsync<'a, C> {
+        Self::with_settings(conn, cache, context, Settings::default())
+    }
+
+    pub(crate) fn with_settings(
+        conn: C,
+        cache: &'a Cache,
+        context: Context,
+        settings: Settings,
+    ) -> MySqlAsync<'a, C> {
         MySqlAsync {
-            backend: MySqlAsyncBackend {
-                conn,
-                cache,
-                context,
-            },
+            backend: MySqlAsyncBackend::new(conn, cache, context, settings),
         }
     }
 
+    /// Settings of this wrapper, e.g. the time zone of the session.
+    pub fn settings(&self) -> &Settings {
+        &self.backend.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.backend.settings
+    }
+
     pub fn set_roles(&mut self, roles: HashSet<String>) -> &mut Self {
         self.backend.context.roles = roles;
         self
@@ -0,0 +1,227 @@
+//! An in-memory connection for tests without database.
+//!
+//! [MockConn] implements [Queryable] and can be wrapped like a real connection.
+//! It records every statement with its parameters and answers with scripted results.
+//! If no result is scripted, a statement returns no rows and an empty OK packet.
+//!
+//! ```rust
+//! use toql_mysql_async::{mock::MockConn, prelude::*};
+//!
+//! let mut conn = MockConn::new();
+//! conn.push_rows(&["count"], vec![vec![Value::UInt(42)]]);
+//!
+//! let mut toql = MySqlAsync::from(conn, &cache);
+//! assert_eq!(toql.count(query!(User, "*")).await?, 42);
+//!
+//! let statements = toql.conn().statements();
+//! assert!(statements[0].sql.starts_with("SELECT COUNT(*)"));
+//! ```
+//!
+//! Enable it with the feature `mock`.
+
+use crate::queryable::{Queryable, RowStream, StatementText};
+use futures_core::future::BoxFuture;
+use mysql_async::{
+    consts::ColumnType,
+    prelude::{FromRow, StatementLike},
+    Column, DriverError, Error, FromRowError, Params, Result, Row, ServerError, Value,
+};
+use std::{collections::VecDeque, sync::Arc};
+
+/// A statement, that was sent to the [MockConn].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedStatement {
+    pub sql: String,
+    pub params: Vec<Value>,
+}
+
+/// A scripted answer of the [MockConn].
+#[derive(Debug)]
+pub enum MockResult {
+    /// Result set
+    Rows(Vec<Row>),
+    /// OK packet
+    Ok {
+        affected_rows: u64,
+        last_insert_id: Option<u64>,
+    },
+    /// Failing statement
+    Error(Error),
+}
+
+#[derive(Debug, Default)]
+pub struct MockConn {
+    statements: Vec<RecordedStatement>,
+    results: VecDeque<MockResult>,
+    affected_rows: u64,
+    last_insert_id: Option<u64>,
+    in_transaction: bool,
+}
+
+impl MockConn {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Script the result for the next unanswered statement.
+    pub fn push_result(&mut self, result: MockResult) -> &mut Self {
+        self.results.push_back(result);
+        self
+    }
+
+    /// Script a result set with the given column names.
+    pub fn push_rows(&mut self, columns: &[&str], rows: Vec<Vec<Value>>) -> &mut Self {
+        let columns: Arc<[Column]> = columns
+            .iter()
+            .map(|c| Column::new(ColumnType::MYSQL_TYPE_VAR_STRING).with_name(c.as_bytes()))
+            .collect::<Vec<_>>()
+            .into();
+        let rows = rows
+            .into_iter()
+            .map(|values| mysql_common::row::new_row(values, columns.clone()))
+            .collect();
+        self.push_result(MockResult::Rows(rows))
+    }
+
+    /// Script an OK packet.
+    pub fn push_ok(&mut self, affected_rows: u64, last_insert_id: Option<u64>) -> &mut Self {
+        self.push_result(MockResult::Ok {
+            affected_rows,
+            last_insert_id,
+        })
+    }
+
+    /// Script a server error, e.g. `1062` for a duplicate key.
+    pub fn push_server_error(&mut self, code: u16, state: &str, message: &str) -> &mut Self {
+        self.push_result(MockResult::Error(
+            ServerError {
+                code,
+                state: state.to_string(),
+                message: message.to_string(),
+            }
+            .into(),
+        ))
+    }
+
+    /// Act like a transaction, e.g. to test savepoints.
+    pub fn set_in_transaction(&mut self, in_transaction: bool) -> &mut Self {
+        self.in_transaction = in_transaction;
+        self
+    }
+
+    /// All statements in the order they were executed.
+    pub fn statements(&self) -> &[RecordedStatement] {
+        &self.statements
+    }
+
+    /// Take the recorded statements and clear the record.
+    pub fn take_statements(&mut self) -> Vec<RecordedStatement> {
+        std::mem::take(&mut self.statements)
+    }
+
+    /// Number of scripted results, that are not consumed yet.
+    pub fn pending_results(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Record statement and return its scripted rows.
+    fn answer(&mut self, sql: String, params: Params) -> Result<Vec<Row>> {
+        let params = match params {
+            Params::Empty => Vec::new(),
+            Params::Positional(values) => values,
+            Params::Named(values) => values.into_iter().map(|(_, v)| v).collect(),
+        };
+        self.statements.push(RecordedStatement { sql, params });
+
+        let (rows, affected_rows, last_insert_id) = match self.results.pop_front() {
+            Some(MockResult::Rows(rows)) => (rows, 0, None),
+            Some(MockResult::Ok {
+                affected_rows,
+                last_insert_id,
+            }) => (Vec::new(), affected_rows, last_insert_id),
+            Some(MockResult::Error(err)) => return Err(err),
+            None => (Vec::new(), 0, None),
+        };
+        self.affected_rows = affected_rows;
+        self.last_insert_id = last_insert_id;
+        Ok(rows)
+    }
+}
+
+fn from_rows<T: FromRow>(rows: Vec<Row>) -> Result<Vec<T>> {
+    rows.into_iter()
+        .map(|r| T::from_row_opt(r).map_err(|FromRowError(row)| DriverError::FromRow { row }.into()))
+        .collect()
+}
+
+impl Queryable for MockConn {
+    fn exec<'a: 'b, 'b, T, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, Result<Vec<T>>>
+    where
+        S: StatementLike + StatementText + 'b,
+        P: Into<Params> + Send + 'b,
+        T: FromRow + Send + 'static,
+    {
+        let rows = self.answer(stmt.statement_text().into_owned(), params.into());
+        Box::pin(async move { from_rows(rows?) })
+    }
+
+    fn exec_first<'a: 'b, 'b, T, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, Result<Option<T>>>
+    where
+        S: StatementLike + StatementText + 'b,
+        P: Into<Params> + Send + 'b,
+        T: FromRow + Send + 'static,
+    {
+        let rows = self.answer(stmt.statement_text().into_owned(), params.into());
+        Box::pin(async move { Ok(from_rows(rows?)?.into_iter().next()) })
+    }
+
+    fn exec_drop<'a: 'b, 'b, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, Result<()>>
+    where
+        S: StatementLike + StatementText + 'b,
+        P: Into<Params> + Send + 'b,
+    {
+        let rows = self.answer(stmt.statement_text().into_owned(), params.into());
+        Box::pin(async move { rows.map(|_| ()) })
+    }
+
+    fn exec_stream<'a: 'b, 'b, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, Result<RowStream<'b>>>
+    where
+        S: StatementLike + StatementText + 'b,
+        P: Into<Params> + Send + 'b,
+    {
+        let rows = self.answer(stmt.statement_text().into_owned(), params.into());
+        Box::pin(async move {
+            let stream: RowStream<'b> = Box::pin(futures_util::stream::iter(rows?.into_iter().map(Ok)));
+            Ok(stream)
+        })
+    }
+
+    fn query_first<'a, T, Q>(&'a mut self, query: Q) -> BoxFuture<'a, Result<Option<T>>>
+    where
+        Q: AsRef<str> + Send + Sync + 'a,
+        T: FromRow + Send + 'static,
+    {
+        let rows = self.answer(query.as_ref().to_string(), Params::Empty);
+        Box::pin(async move { Ok(from_rows(rows?)?.into_iter().next()) })
+    }
+
+    fn query_drop<'a, Q>(&'a mut self, query: Q) -> BoxFuture<'a, Result<()>>
+    where
+        Q: AsRef<str> + Send + Sync + 'a,
+    {
+        let rows = self.answer(query.as_ref().to_string(), Params::Empty);
+        Box::pin(async move { rows.map(|_| ()) })
+    }
+
+    fn affected_rows(&self) -> u64 {
+        self.affected_rows
+    }
+
+    fn last_insert_id(&self) -> Option<u64> {
+        self.last_insert_id
+    }
+
+    fn in_transaction(&self) -> bool {
+        self.in_transaction
+    }
+}
@@ -0,0 +1,245 @@
+//! Strategies to count the records of a page.
+//!
+//! A [Page::Counted](toql::prelude::Page::Counted) needs the number of records
+//! that the query would return without page limitation.
+//! MySQL offers different ways to get this number, they all return the same [PageCounts](toql::prelude::PageCounts).
+//!
+//! Besides pages with offset, [load_paged](crate::MySqlAsync::load_paged) loads keyset pages,
+//! see [keyset](crate::keyset).
+
+use crate::{
+    error::ToqlMySqlAsyncError,
+    insert::{count_placeholders, scan},
+    keyset::Cursor,
+    queryable::Queryable,
+    result::Result,
+    row::Row,
+    MySqlAsync,
+};
+use std::borrow::Borrow;
+use toql::{
+    backend::load::load,
+    keyed::Keyed,
+    page_counts::PageCounts,
+    prelude::{KeyFields, Sql},
+    query::{field::Field, Query},
+    table_mapper::mapped::Mapped,
+    toql_api::{load::Load, ToqlApi},
+};
+
+/// A page of entities.
+///
+/// Like [Page](toql::prelude::Page) with an additional keyset page.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Page {
+    /// Page from `start` with up to `n` entities
+    Uncounted(u64, u16),
+    /// Page from `start` with up to `n` entities and [PageCounts]
+    Counted(u64, u16),
+    /// Page with up to `n` entities after the cursor, the first page has no cursor
+    Keyset(Option<Cursor>, u16),
+}
+
+impl From<toql::page::Page> for Page {
+    fn from(page: toql::page::Page) -> Self {
+        match page {
+            toql::page::Page::Uncounted(start, n) => Page::Uncounted(start, n),
+            toql::page::Page::Counted(start, n) => Page::Counted(start, n),
+        }
+    }
+}
+
+/// Counts and cursor of a loaded [Page].
+pub struct PageInfo {
+    /// Counts of a [Page::Counted]
+    pub counts: Option<PageCounts>,
+    /// Cursor for the keyset page after a [Page::Keyset] or `None`, if this is the last page
+    pub next: Option<Cursor>,
+}
+
+/// Strategy to count the unlimited records of a counted page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageCountStrategy {
+    /// Run a separate `COUNT(*)` query with the same predicate.
+    CountQuery,
+    /// Add `COUNT(*) OVER()` as window column to the page query.
+    /// Requires MySQL 8.0 or MariaDB 10.2.
+    WindowCount,
+    /// Use the `SQL_CALC_FOUND_ROWS` modifier and `SELECT FOUND_ROWS()`.
+    /// Deprecated since MySQL 8.0.17.
+    FoundRows,
+}
+
+impl Default for PageCountStrategy {
+    fn default() -> Self {
+        PageCountStrategy::CountQuery
+    }
+}
+
+impl<'a, C> MySqlAsync<'a, C>
+where
+    C: Queryable + Send,
+{
+    /// Load a page of entities with offset or after a keyset cursor.
+    ///
+    /// A keyset page is ordered like the query, the key fields are added to the ordering
+    /// to break ties. Order fields must not be `NULL`.
+    /// [PageInfo::next] holds the cursor for the next keyset page.
+    #[tracing::instrument(skip(self, query), fields(ty = %<T as Mapped>::type_name()))]
+    pub async fn load_paged<T, B>(&mut self, query: B, page: Page) -> Result<(Vec<T>, PageInfo)>
+    where
+        T: Load<Row, ToqlMySqlAsyncError>,
+        B: Borrow<Query<T>> + Send + Sync,
+        <T as Keyed>::Key: KeyFields,
+    {
+        let (after, size) = match page {
+            Page::Uncounted(start, n) => {
+                let page = toql::page::Page::Uncounted(start, n);
+                let (entities, counts) = self.load_page(query, page).await?;
+                return Ok((entities, PageInfo { counts, next: None }));
+            }
+            Page::Counted(start, n) => {
+                let page = toql::page::Page::Counted(start, n);
+                let (entities, counts) = self.load_page(query, page).await?;
+                return Ok((entities, PageInfo { counts, next: None }));
+            }
+            Page::Keyset(after, size) => (after, size),
+        };
+
+        // Keys come last in the ordering, so that every entity has a unique position
+        let fields = <<T as Keyed>::Key as KeyFields>::fields();
+        let mut keyset_query = self
+            .backend
+            .settings
+            .row
+            .converters
+            .convert_query(&<T as Mapped>::type_name(), query.borrow())
+            .unwrap_or_else(|| query.borrow().clone());
+        for (i, field) in fields.iter().enumerate() {
+            let priority = u8::MAX - (fields.len() - 1 - i) as u8;
+            keyset_query = keyset_query.and(Field::from(field.as_str()).asc(priority));
+        }
+
+        self.backend.keyset_pending = Some(after);
+        self.backend.keyset_cursor = None;
+        self.backend.mapper = Some(<T as Mapped>::type_name());
+        let result = load(
+            &mut self.backend,
+            &keyset_query,
+            Some(toql::page::Page::Uncounted(0, size)),
+        )
+        .await;
+        self.backend.keyset_pending = None;
+        self.backend.mapper = None;
+        let cursor = self.backend.keyset_cursor.take();
+        let (entities, _) = result.map_err(|e| e.with_entity("load", <T as Mapped>::type_name()))?;
+
+        let next = if entities.len() < size as usize {
+            None
+        } else {
+            cursor
+        };
+        Ok((entities, PageInfo { counts: None, next }))
+    }
+}
+
+/// Count query for the records of a page query without page limitation.
+///
+/// The select list is replaced by a constant, so that columns of joined tables
+/// with the same name don't clash in the derived table:
+/// `SELECT COUNT(*) FROM (SELECT 1 FROM ... WHERE ...) AS toql_page`.
+/// Distinct columns are kept, but renamed.
+pub(crate) fn unlimited_count_sql(sql: Sql) -> Sql {
+    let Sql(sql_stmt, mut args) = sql;
+
+    // Order and page limitation are not needed to count
+    let mut stmt = sql_stmt.as_str();
+    for keyword in &["LIMIT", "ORDER"] {
+        if let Some(&pos) = keyword_positions(stmt, keyword).last() {
+            stmt = &stmt[..pos];
+        }
+    }
+    let stmt = stmt.trim_end();
+    args.truncate(count_placeholders(stmt));
+
+    let select = keyword_positions(stmt, "SELECT").first().copied();
+    let from = keyword_positions(stmt, "FROM").first().copied();
+    let (list_start, list_end) = match (select, from) {
+        (Some(select), Some(from)) if select < from => (select + "SELECT".len(), from),
+        _ => return Sql(format!("SELECT COUNT(*) FROM ({}) AS toql_page", stmt), args),
+    };
+
+    let list = &stmt[list_start..list_end];
+    let columns = match keyword_positions(list, "DISTINCT").first() {
+        Some(&distinct) => format!(
+            " DISTINCT {} ",
+            split_list(&list[distinct + "DISTINCT".len()..])
+                .iter()
+                .enumerate()
+                .map(|(i, c)| format!("{} AS c{}", without_alias(c), i))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        None => {
+            // Drop the arguments of the replaced columns
+            let before = count_placeholders(&stmt[..list_start]);
+            args.drain(before..before + count_placeholders(list));
+            " 1 ".to_string()
+        }
+    };
+
+    Sql(
+        format!(
+            "SELECT COUNT(*) FROM ({}{}{}) AS toql_page",
+            &stmt[..list_start],
+            columns,
+            &stmt[list_end..]
+        ),
+        args,
+    )
+}
+
+/// Byte positions of a keyword outside of quotes and parentheses.
+pub(crate) fn keyword_positions(sql: &str, keyword: &str) -> Vec<usize> {
+    let is_word = |pos: Option<usize>| {
+        pos.and_then(|p| sql.as_bytes().get(p))
+            .map_or(false, |b| b.is_ascii_alphanumeric() || *b == b'_')
+    };
+    let mut positions = Vec::new();
+    scan(sql, |i, _, depth| {
+        if depth == 0
+            && sql
+                .get(i..i + keyword.len())
+                .map_or(false, |w| w.eq_ignore_ascii_case(keyword))
+            && !is_word(i.checked_sub(1))
+            && !is_word(Some(i + keyword.len()))
+        {
+            positions.push(i);
+        }
+        true
+    });
+    positions
+}
+
+/// Split a select list at the commas outside of quotes and parentheses.
+pub(crate) fn split_list(list: &str) -> Vec<&str> {
+    let mut columns = Vec::new();
+    let mut start = 0;
+    scan(list, |i, c, depth| {
+        if c == ',' && depth == 0 {
+            columns.push(list[start..i].trim());
+            start = i + 1;
+        }
+        true
+    });
+    columns.push(list[start..].trim());
+    columns
+}
+
+/// Select expression without its alias.
+fn without_alias(column: &str) -> &str {
+    match keyword_positions(column, "AS").last() {
+        Some(&pos) => column[..pos].trim_end(),
+        None => column,
+    }
+}
@@ -1,7 +1,14 @@
 // Reexport for derive produced code
 pub use crate::error::ToqlMySqlAsyncError;
 pub use crate::queryable::Queryable;
-pub use crate::row::Row;
+pub use crate::row::{Enum, Numeric, Row};
+#[cfg(feature = "json")]
+pub use crate::row::Json;
+#[cfg(feature = "bitflags")]
+pub use crate::row::SetFlags;
+#[cfg(feature = "uuid")]
+pub use crate::row::SwappedUuid;
 pub use crate::MySqlAsync;
+pub use crate::pool::MySqlAsyncPool;
 pub use mysql_async::Conn;
 pub use mysql_async::Value;
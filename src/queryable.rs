@@ -1,12 +1,69 @@
 //! A reimplementation of [Queryable](crate::mysql_async::prelude::Queryable) that allows calls on [Conn] and &mut [Conn].
 use mysql_async::prelude::{FromRow, StatementLike};
-use mysql_async::{Result, Conn, Params, Transaction};
+use mysql_async::consts::StatusFlags;
+use mysql_async::{BinaryProtocol, Result, Conn, Params, QueryResult, Row, Statement, Transaction};
+use std::{borrow::Cow, sync::Arc};
 type BoxFuture<'a, T> = futures_core::future::BoxFuture<'a, Result<T>>;
 
+/// A stream of rows, that are read from the server on demand.
+pub type RowStream<'a> = futures_core::stream::BoxStream<'a, Result<Row>>;
+
+/// SQL text of a statement, so that implementations can inspect or record it.
+pub trait StatementText {
+    fn statement_text(&self) -> Cow<'_, str>;
+}
+
+impl StatementText for &'_ str {
+    fn statement_text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+impl StatementText for String {
+    fn statement_text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+impl StatementText for Cow<'_, str> {
+    fn statement_text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+impl StatementText for Box<str> {
+    fn statement_text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+impl StatementText for Arc<str> {
+    fn statement_text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+impl StatementText for &'_ [u8] {
+    fn statement_text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self)
+    }
+}
+impl StatementText for Vec<u8> {
+    fn statement_text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self)
+    }
+}
+// The SQL of a prepared statement is not known anymore
+impl StatementText for Statement {
+    fn statement_text(&self) -> Cow<'_, str> {
+        Cow::Owned(format!("<prepared statement {}>", self.id()))
+    }
+}
+impl<T: StatementText> StatementText for &'_ T {
+    fn statement_text(&self) -> Cow<'_, str> {
+        (**self).statement_text()
+    }
+}
+
 pub trait Queryable {
     fn exec<'a: 'b, 'b, T, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, Vec<T>>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
         T: FromRow + Send + 'static;
 
@@ -16,25 +73,71 @@ pub trait Queryable {
         params: P,
     ) -> BoxFuture<'b, Option<T>>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
         T: FromRow + Send + 'static;
 
     fn exec_drop<'a: 'b, 'b, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, ()>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b;
 
+    /// Execute statement and stream the result rows.
+    ///
+    /// By default all rows are loaded with [exec](Queryable::exec) before they are streamed.
+    fn exec_stream<'a: 'b, 'b, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, RowStream<'b>>
+    where
+        S: StatementLike + StatementText + 'b,
+        P: Into<Params> + Send + 'b,
+    {
+        let rows = self.exec::<Row, S, P>(stmt, params);
+        Box::pin(async move {
+            let stream: RowStream<'b> =
+                Box::pin(futures_util::stream::iter(rows.await?.into_iter().map(Ok)));
+            Ok(stream)
+        })
+    }
+
     fn query_first<'a, T, Q>(&'a mut self, query: Q) -> BoxFuture<'a, Option<T>>
     where
         Q: AsRef<str> + Send + Sync + 'a,
         T: FromRow + Send + 'static;
+
+    /// Run a query without result, e.g. `SAVEPOINT`.
+    ///
+    /// By default the query runs with [query_first](Queryable::query_first).
+    fn query_drop<'a, Q>(&'a mut self, query: Q) -> BoxFuture<'a, ()>
+    where
+        Q: AsRef<str> + Send + Sync + 'a,
+    {
+        let row = self.query_first::<Row, Q>(query);
+        Box::pin(async move { row.await.map(|_| ()) })
+    }
+
+    /// Number of affected rows from the OK packet of the last statement.
+    ///
+    /// The default is 0, so inserts return no generated ids.
+    fn affected_rows(&self) -> u64 {
+        0
+    }
+
+    /// First generated id from the OK packet of the last statement.
+    fn last_insert_id(&self) -> Option<u64> {
+        None
+    }
+
+    /// True, if statements run inside a transaction.
+    ///
+    /// The default is false, so single row inserts run in their own transaction.
+    fn in_transaction(&self) -> bool {
+        false
+    }
 }
 
 impl Queryable for Conn {
     fn exec<'a: 'b, 'b, T, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, Vec<T>>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
         T: FromRow + Send + 'static,
     {
@@ -42,7 +145,7 @@ impl Queryable for Conn {
     }
     fn exec_first<'a: 'b, 'b, T, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, Option<T>>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
         T: FromRow + Send + 'static,
     {
@@ -51,12 +154,23 @@ impl Queryable for Conn {
 
     fn exec_drop<'a: 'b, 'b, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, ()>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
     {
         mysql_async::prelude::Queryable::exec_drop(self, stmt, params)
     }
 
+    fn exec_stream<'a: 'b, 'b, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, RowStream<'b>>
+    where
+        S: StatementLike + StatementText + 'b,
+        P: Into<Params> + Send + 'b,
+    {
+        Box::pin(async move {
+            let result = mysql_async::prelude::Queryable::exec_iter(self, stmt, params).await?;
+            Ok(row_stream(result))
+        })
+    }
+
     fn query_first<'a, T, Q>(&'a mut self, query: Q) -> BoxFuture<'a, Option<T>>
     where
         Q: AsRef<str> + Send + Sync + 'a,
@@ -64,12 +178,31 @@ impl Queryable for Conn {
     {
         mysql_async::prelude::Queryable::query_first(self, query)
     }
+
+    fn query_drop<'a, Q>(&'a mut self, query: Q) -> BoxFuture<'a, ()>
+    where
+        Q: AsRef<str> + Send + Sync + 'a,
+    {
+        mysql_async::prelude::Queryable::query_drop(self, query)
+    }
+
+    fn affected_rows(&self) -> u64 {
+        Conn::affected_rows(self)
+    }
+
+    fn last_insert_id(&self) -> Option<u64> {
+        Conn::last_insert_id(self)
+    }
+
+    fn in_transaction(&self) -> bool {
+        conn_in_transaction(self)
+    }
 }
 
 impl Queryable for &mut Conn {
     fn exec<'a: 'b, 'b, T, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, Vec<T>>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
         T: FromRow + Send + 'static,
     {
@@ -77,7 +210,7 @@ impl Queryable for &mut Conn {
     }
     fn exec_first<'a: 'b, 'b, T, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, Option<T>>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
         T: FromRow + Send + 'static,
     {
@@ -85,12 +218,23 @@ impl Queryable for &mut Conn {
     }
     fn exec_drop<'a: 'b, 'b, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, ()>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
     {
         mysql_async::prelude::Queryable::exec_drop(*self, stmt, params)
     }
 
+    fn exec_stream<'a: 'b, 'b, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, RowStream<'b>>
+    where
+        S: StatementLike + StatementText + 'b,
+        P: Into<Params> + Send + 'b,
+    {
+        Box::pin(async move {
+            let result = mysql_async::prelude::Queryable::exec_iter(*self, stmt, params).await?;
+            Ok(row_stream(result))
+        })
+    }
+
     fn query_first<'a, T, Q>(&'a mut self, query: Q) -> BoxFuture<'a, Option<T>>
     where
         Q: AsRef<str> + Send + Sync + 'a,
@@ -98,12 +242,31 @@ impl Queryable for &mut Conn {
     {
         mysql_async::prelude::Queryable::query_first(*self, query)
     }
+
+    fn query_drop<'a, Q>(&'a mut self, query: Q) -> BoxFuture<'a, ()>
+    where
+        Q: AsRef<str> + Send + Sync + 'a,
+    {
+        mysql_async::prelude::Queryable::query_drop(*self, query)
+    }
+
+    fn affected_rows(&self) -> u64 {
+        Conn::affected_rows(self)
+    }
+
+    fn last_insert_id(&self) -> Option<u64> {
+        Conn::last_insert_id(self)
+    }
+
+    fn in_transaction(&self) -> bool {
+        conn_in_transaction(self)
+    }
 }
 
 impl Queryable for Transaction<'_> {
     fn exec<'a: 'b, 'b, T, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, Vec<T>>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
         T: FromRow + Send + 'static,
     {
@@ -111,7 +274,7 @@ impl Queryable for Transaction<'_> {
     }
     fn exec_first<'a: 'b, 'b, T, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, Option<T>>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
         T: FromRow + Send + 'static,
     {
@@ -119,12 +282,23 @@ impl Queryable for Transaction<'_> {
     }
     fn exec_drop<'a: 'b, 'b, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, ()>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
     {
         mysql_async::prelude::Queryable::exec_drop(self, stmt, params)
     }
 
+    fn exec_stream<'a: 'b, 'b, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, RowStream<'b>>
+    where
+        S: StatementLike + StatementText + 'b,
+        P: Into<Params> + Send + 'b,
+    {
+        Box::pin(async move {
+            let result = mysql_async::prelude::Queryable::exec_iter(self, stmt, params).await?;
+            Ok(row_stream(result))
+        })
+    }
+
     fn query_first<'a, T, Q>(&'a mut self, query: Q) -> BoxFuture<'a, Option<T>>
     where
         Q: AsRef<str> + Send + Sync + 'a,
@@ -132,12 +306,31 @@ impl Queryable for Transaction<'_> {
     {
         mysql_async::prelude::Queryable::query_first(self, query)
     }
+
+    fn query_drop<'a, Q>(&'a mut self, query: Q) -> BoxFuture<'a, ()>
+    where
+        Q: AsRef<str> + Send + Sync + 'a,
+    {
+        mysql_async::prelude::Queryable::query_drop(self, query)
+    }
+
+    fn affected_rows(&self) -> u64 {
+        Conn::affected_rows(self)
+    }
+
+    fn last_insert_id(&self) -> Option<u64> {
+        Conn::last_insert_id(self)
+    }
+
+    fn in_transaction(&self) -> bool {
+        true
+    }
 }
 
 impl Queryable for &mut Transaction<'_> {
     fn exec<'a: 'b, 'b, T, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, Vec<T>>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
         T: FromRow + Send + 'static,
     {
@@ -145,7 +338,7 @@ impl Queryable for &mut Transaction<'_> {
     }
     fn exec_first<'a: 'b, 'b, T, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, Option<T>>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
         T: FromRow + Send + 'static,
     {
@@ -153,12 +346,23 @@ impl Queryable for &mut Transaction<'_> {
     }
     fn exec_drop<'a: 'b, 'b, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, ()>
     where
-        S: StatementLike + 'b,
+        S: StatementLike + StatementText + 'b,
         P: Into<Params> + Send + 'b,
     {
         mysql_async::prelude::Queryable::exec_drop(*self, stmt, params)
     }
 
+    fn exec_stream<'a: 'b, 'b, S, P>(&'a mut self, stmt: S, params: P) -> BoxFuture<'b, RowStream<'b>>
+    where
+        S: StatementLike + StatementText + 'b,
+        P: Into<Params> + Send + 'b,
+    {
+        Box::pin(async move {
+            let result = mysql_async::prelude::Queryable::exec_iter(*self, stmt, params).await?;
+            Ok(row_stream(result))
+        })
+    }
+
     fn query_first<'a, T, Q>(&'a mut self, query: Q) -> BoxFuture<'a, Option<T>>
     where
         Q: AsRef<str> + Send + Sync + 'a,
@@ -166,4 +370,39 @@ impl Queryable for &mut Transaction<'_> {
     {
         mysql_async::prelude::Queryable::query_first(*self, query)
     }
+
+    fn query_drop<'a, Q>(&'a mut self, query: Q) -> BoxFuture<'a, ()>
+    where
+        Q: AsRef<str> + Send + Sync + 'a,
+    {
+        mysql_async::prelude::Queryable::query_drop(*self, query)
+    }
+
+    fn affected_rows(&self) -> u64 {
+        Conn::affected_rows(self)
+    }
+
+    fn last_insert_id(&self) -> Option<u64> {
+        Conn::last_insert_id(self)
+    }
+
+    fn in_transaction(&self) -> bool {
+        true
+    }
+}
+
+fn row_stream<'a>(result: QueryResult<'a, 'static, BinaryProtocol>) -> RowStream<'a> {
+    Box::pin(futures_util::stream::try_unfold(
+        result,
+        |mut result| async move { Ok(result.next().await?.map(|row| (row, result))) },
+    ))
+}
+
+/// True, if the last OK packet of the server reports an open transaction,
+/// e.g. after `START TRANSACTION` was sent as query.
+fn conn_in_transaction(conn: &Conn) -> bool {
+    conn.last_ok_packet().map_or(false, |ok| {
+        ok.status_flags()
+            .contains(StatusFlags::SERVER_STATUS_IN_TRANS)
+    })
 }
@@ -0,0 +1,118 @@
+//! Retry policy for transactions.
+//!
+//! Under load InnoDB resolves deadlocks by rolling back one of the transactions.
+//! With a [RetryPolicy] [MySqlAsync::retry_transaction](crate::MySqlAsync::retry_transaction)
+//! runs the whole transaction closure again, if the server reports a deadlock or a lock wait timeout.
+//!
+//! ```rust
+//! use toql_mysql_async::retry::RetryPolicy;
+//! use std::time::Duration;
+//!
+//! toql.settings_mut().set_retry_policy(Some(
+//!     RetryPolicy::new(5).with_backoff(Duration::from_millis(20), Duration::from_secs(1)),
+//! ));
+//! toql.retry_transaction(TxOpts::default(), |tx| Box::pin(async move {
+//!     tx.update_many(&mut payments, fields!(top)).await
+//! })).await?;
+//! ```
+use std::{
+    cell::Cell,
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// How often and when a failed transaction is run again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry, it doubles with every further retry
+    pub initial_backoff: Duration,
+    /// Upper limit of the delay
+    pub max_backoff: Duration,
+    /// Randomize delay between half and full backoff, so that conflicting transactions don't retry in lockstep
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(2),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy with the given maximum number of attempts and default backoff.
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts,
+            ..Default::default()
+        }
+    }
+
+    /// Policy, that runs a transaction only once.
+    pub fn never() -> Self {
+        RetryPolicy::new(1)
+    }
+
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Delay after the given failed attempt, starting with 1.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self
+            .initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+        if self.jitter {
+            delay.mul_f64(0.5 + random_fraction() / 2.0)
+        } else {
+            delay
+        }
+    }
+}
+
+thread_local! {
+    // Seeded per thread, so concurrent retries draw different numbers
+    static RANDOM_STATE: Cell<u64> = Cell::new(random_seed());
+}
+
+/// Random seed from the randomly keyed hasher of the standard library.
+fn random_seed() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    hasher.finish() | 1
+}
+
+/// Number in `[0, 1)`, good enough to spread retries.
+fn random_fraction() -> f64 {
+    RANDOM_STATE.with(|state| {
+        // Xorshift, every call advances the state of the thread
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    })
+}
@@ -2,28 +2,479 @@
 //! This allows to implement the conversion trait [FromRow]( toql::from_row::FromRow) for basic data types
 //! without violating the orphan rule.
 
-use crate::error::ToqlMySqlAsyncError;
-use mysql_common::chrono::NaiveDateTime;
-use toql::sql_builder::select_stream::Select;
+use crate::{convert::Converters, error::ToqlMySqlAsyncError};
+use mysql_async::{
+    consts::{ColumnFlags, ColumnType},
+    prelude::FromValue,
+    Value,
+};
+use mysql_common::chrono::{
+    DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+};
+use mysql_common::prelude::ColumnIndex;
+use std::{
+    collections::HashSet,
+    convert::TryFrom,
+    fmt::Display,
+    hash::Hash,
+    ops::Deref,
+    str::FromStr,
+    sync::Arc,
+};
+use toql::{from_row::FromRow, sql_arg::SqlArg, sql_builder::select_stream::Select};
 
+/// Row of a result set with the conversion settings of its connection.
+///
+/// Build it with [Row::new] or `From<mysql_async::Row>`, the tuple constructor is private.
 #[derive(Debug)]
-pub struct Row(pub mysql_async::Row);
+pub struct Row(
+    pub mysql_async::Row,
+    pub(crate) Arc<RowSettings>,
+    pub(crate) Option<FieldPaths>,
+);
+
+/// Toql field paths of the columns of a result set, `None` for unmapped columns.
+pub(crate) type FieldPaths = Arc<[Option<String>]>;
+
+impl Row {
+    /// Wrap a MySQL row with the default conversion settings.
+    pub fn new(row: mysql_async::Row) -> Self {
+        Row(row, Arc::default(), None)
+    }
+
+    /// The MySQL row without conversion settings.
+    pub fn into_inner(self) -> mysql_async::Row {
+        self.0
+    }
+
+    /// Get the value of a column by index or name.
+    ///
+    /// Returns `None` for `NULL` and an error, if the column doesn't exist
+    /// or its value can't be converted into `T`.
+    /// Registered converters are applied, the zero date policy to dates and datetimes.
+    ///
+    /// ```rust
+    /// let id: Option<u64> = row.try_get("id")?;
+    /// let name: Option<String> = row.try_get(1)?;
+    /// ```
+    pub fn try_get<T, I>(&self, column: I) -> Result<Option<T>, ToqlMySqlAsyncError>
+    where
+        T: FromValue,
+        I: ColumnIndex + Display,
+    {
+        let index = column
+            .idx(self.0.columns_ref())
+            .ok_or_else(|| ToqlMySqlAsyncError::ColumnNotFoundError(column.to_string()))?;
+        column_value::<T>(self, index)?
+            .map(|v| {
+                // `BIT(n)` is decoded for integers and `bool`, other types get the raw bytes
+                let bits = if is_bit_column(self, index) {
+                    mysql_async::from_value_opt::<T>(bit_value(self, index, v.clone())).ok()
+                } else {
+                    None
+                };
+                let v = match bits {
+                    Some(bits) => return Ok(Some(bits)),
+                    None => match mysql_async::from_value_opt::<T>(v) {
+                        Ok(v) => return Ok(Some(v)),
+                        Err(mysql_async::FromValueError(v)) => v,
+                    },
+                };
+                // Only dates and datetimes fail on zero dates, retry with the policy
+                date_value(self, index, v)?
+                    .map(|v| {
+                        mysql_async::from_value_opt::<T>(v)
+                            .map_err(|_| deserialize_error::<T>(self, index))
+                    })
+                    .transpose()
+            })
+            .transpose()
+            .map(Option::flatten)
+    }
+}
+
+impl From<mysql_async::Row> for Row {
+    fn from(row: mysql_async::Row) -> Self {
+        Row::new(row)
+    }
+}
+
+/// Settings to convert the values of a row.
+#[derive(Debug, Clone)]
+pub(crate) struct RowSettings {
+    /// Time zone of the session, in which the server returns `DATETIME` and `TIMESTAMP` values
+    pub(crate) time_zone: FixedOffset,
+    pub(crate) converters: Converters,
+    pub(crate) zero_date_policy: ZeroDatePolicy,
+    pub(crate) numeric_mode: NumericMode,
+}
+
+/// Handling of zero dates like `0000-00-00` and invalid dates like `2022-02-30`,
+/// that MySQL stores depending on its SQL mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZeroDatePolicy {
+    /// Fail with [ZeroDateError](ToqlMySqlAsyncError::ZeroDateError)
+    Error,
+    /// Load as `NULL`, so that optional fields become `None`
+    Null,
+    /// Load as the given datetime, e.g. the Unix epoch
+    Sentinel(NaiveDateTime),
+}
+
+impl Default for ZeroDatePolicy {
+    fn default() -> Self {
+        ZeroDatePolicy::Error
+    }
+}
+
+/// Handling of numbers, that don't fit exactly into the type of their field,
+/// e.g. a `BIGINT UNSIGNED` in an `i64` or a `DOUBLE` in an `f32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericMode {
+    /// Fail with [NumericError](ToqlMySqlAsyncError::NumericError) on overflow or loss of precision
+    Strict,
+    /// Clamp to the range of the field type or round to its precision and log a warning
+    Lenient,
+}
+
+impl Default for NumericMode {
+    fn default() -> Self {
+        NumericMode::Strict
+    }
+}
+
+impl Default for RowSettings {
+    fn default() -> Self {
+        RowSettings {
+            time_zone: FixedOffset::east_opt(0).unwrap(),
+            converters: Converters::default(),
+            zero_date_policy: ZeroDatePolicy::default(),
+            numeric_mode: NumericMode::default(),
+        }
+    }
+}
+
+/// Skip the next column and return 1, if it is selected.
+pub(crate) fn forward_column<'a, I>(iter: &mut I) -> Result<usize, ToqlMySqlAsyncError>
+where
+    I: Iterator<Item = &'a Select>,
+{
+    if iter
+        .next()
+        .ok_or(toql::error::ToqlError::DeserializeError(
+            toql::deserialize::error::DeserializeError::StreamEnd,
+        ))?
+        .is_selected()
+    {
+        Ok(1)
+    } else {
+        Ok(0)
+    }
+}
+
+/// Take the value of the next column, that is deserialized into `T`.
+/// Return None, if unselected or column is null.
+pub(crate) fn next_value<'a, T, I>(
+    row: &Row,
+    i: &mut usize,
+    iter: &mut I,
+) -> Result<Option<Value>, ToqlMySqlAsyncError>
+where
+    T: ?Sized,
+    I: Iterator<Item = &'a Select> + Clone,
+{
+    if forward_column(iter)? == 0 {
+        return Ok(None);
+    }
+    let v = column_value::<T>(row, *i)?;
+    *i += 1;
+    Ok(v)
+}
+
+/// Take the value of the next column, that is deserialized into a date or datetime `T`.
+/// Zero dates and invalid dates are handled according to the [ZeroDatePolicy].
+pub(crate) fn next_date_value<'a, T, I>(
+    row: &Row,
+    i: &mut usize,
+    iter: &mut I,
+) -> Result<Option<Value>, ToqlMySqlAsyncError>
+where
+    T: ?Sized,
+    I: Iterator<Item = &'a Select> + Clone,
+{
+    let index = *i;
+    match next_value::<T, _>(row, i, iter)? {
+        Some(v) => date_value(row, index, v),
+        None => Ok(None),
+    }
+}
+
+/// Value of the column at `index` after conversion.
+/// Return None, if column is null.
+fn column_value<T: ?Sized>(row: &Row, index: usize) -> Result<Option<Value>, ToqlMySqlAsyncError> {
+    let mut v = row
+        .0
+        .as_ref(index)
+        .ok_or_else(|| deserialize_error::<T>(row, index))?
+        .to_owned();
+    let column = row.0.columns_ref().get(index);
+    if let Some(column) = column {
+        v = row.1.converters.convert_value(column, v);
+    }
+    match v {
+        Value::NULL => Ok(None),
+        v => Ok(Some(v)),
+    }
+}
+
+/// Value of a date column for a date or datetime field.
+/// Zero dates and invalid dates are handled according to the [ZeroDatePolicy],
+/// fields of other types, e.g. `String`, get the value unchanged.
+fn date_value(row: &Row, index: usize, v: Value) -> Result<Option<Value>, ToqlMySqlAsyncError> {
+    let column = match row.0.columns_ref().get(index) {
+        Some(column) if is_date_column(column) && is_invalid_date(&v) => column,
+        _ => return Ok(Some(v)),
+    };
+    match row.1.zero_date_policy {
+        ZeroDatePolicy::Error => Err(ToqlMySqlAsyncError::ZeroDateError {
+            field: field_path(row, index),
+            key: row_key(row, column),
+            value: String::from_utf8_lossy(&text_value(&v)).to_string(),
+        }),
+        ZeroDatePolicy::Null => Ok(None),
+        ZeroDatePolicy::Sentinel(datetime) => Ok(Some(Value::from(datetime))),
+    }
+}
+
+/// Returns true, if the column at `index` is a `BIT(n)` column.
+fn is_bit_column(row: &Row, index: usize) -> bool {
+    row.0.columns_ref().get(index).map(|c| c.column_type()) == Some(ColumnType::MYSQL_TYPE_BIT)
+}
 
+/// Value of a `BIT(n)` column as unsigned integer for integer and `bool` fields.
+/// Other values are returned unchanged.
+fn bit_value(row: &Row, index: usize, v: Value) -> Value {
+    match v {
+        // `BIT(n)` is sent as big endian bytes
+        Value::Bytes(b) if b.len() <= 8 && is_bit_column(row, index) => {
+            Value::UInt(b.iter().fold(0, |n, b| n << 8 | u64::from(*b)))
+        }
+        v => v,
+    }
+}
+
+fn is_date_column(column: &mysql_async::Column) -> bool {
+    matches!(
+        column.column_type(),
+        ColumnType::MYSQL_TYPE_DATE
+            | ColumnType::MYSQL_TYPE_NEWDATE
+            | ColumnType::MYSQL_TYPE_DATETIME
+            | ColumnType::MYSQL_TYPE_DATETIME2
+            | ColumnType::MYSQL_TYPE_TIMESTAMP
+            | ColumnType::MYSQL_TYPE_TIMESTAMP2
+    )
+}
+
+/// Returns true for zero dates and dates, that don't exist in the calendar.
+pub(crate) fn is_invalid_date(v: &Value) -> bool {
+    let (year, month, day) = match v {
+        Value::Date(year, month, day, ..) => (i32::from(*year), u32::from(*month), u32::from(*day)),
+        Value::Bytes(b) => {
+            // Text protocol: YYYY-MM-DD[ hh:mm:ss[.ffffff]]
+            let date = String::from_utf8_lossy(b);
+            let mut parts = date
+                .get(..10)
+                .unwrap_or_default()
+                .split('-')
+                .map(|p| p.parse::<u32>().ok());
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(Some(year)), Some(Some(month)), Some(Some(day))) => {
+                    (year as i32, month, day)
+                }
+                _ => return false,
+            }
+        }
+        _ => return false,
+    };
+    NaiveDate::from_ymd_opt(year, month, day).is_none()
+}
+
+/// Text of a value for error messages.
+fn text_value(v: &Value) -> Vec<u8> {
+    match v {
+        Value::Bytes(b) => b.clone(),
+        Value::Date(year, month, day, hour, minute, second, _) => format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            year, month, day, hour, minute, second
+        )
+        .into_bytes(),
+        v => v.as_sql(false).into_bytes(),
+    }
+}
+
+/// Primary key of the row, that contains the column.
+fn row_key(row: &Row, column: &mysql_async::Column) -> String {
+    let key = row
+        .0
+        .columns_ref()
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| {
+            c.flags().contains(ColumnFlags::PRI_KEY_FLAG)
+                && c.org_table_ref() == column.org_table_ref()
+        })
+        .map(|(i, c)| {
+            let value = row.0.as_ref(i).map(text_value).unwrap_or_default();
+            format!("{}={}", c.org_name_str(), String::from_utf8_lossy(&value))
+        })
+        .collect::<Vec<_>>();
+    if key.is_empty() {
+        "unknown".to_string()
+    } else {
+        key.join(", ")
+    }
+}
+
+/// Name of the column at `index`.
+pub(crate) fn column_name(row: &Row, index: usize) -> String {
+    row.0
+        .columns_ref()
+        .get(index)
+        .map(|c| c.name_str().to_string())
+        .unwrap_or_else(|| index.to_string())
+}
+
+/// Toql field path of the column at `index`, e.g. `Payment.account_name`.
+/// Columns, that are not mapped to a field of the loaded entity, are named by table and column.
+fn field_path(row: &Row, index: usize) -> String {
+    if let Some(path) = row.2.as_ref().and_then(|p| p.get(index)).and_then(Option::as_ref) {
+        return path.clone();
+    }
+    let column = match row.0.columns_ref().get(index) {
+        Some(column) => column,
+        None => return format!("column {}", index),
+    };
+    // Original names are empty for computed columns
+    let table = match column.org_table_ref() {
+        t if t.is_empty() => column.table_str(),
+        _ => column.org_table_str(),
+    };
+    let name = match column.org_name_ref() {
+        n if n.is_empty() => column.name_str(),
+        _ => column.org_name_str(),
+    };
+    if table.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", table, name)
+    }
+}
+
+/// Error for the column at `index`, that can't be deserialized into `T`.
+pub(crate) fn deserialize_error<T: ?Sized>(row: &Row, index: usize) -> ToqlMySqlAsyncError {
+    let expected = short_type_name(std::any::type_name::<T>());
+    match row.0.columns_ref().get(index) {
+        Some(column) => ToqlMySqlAsyncError::DeserializeError {
+            index,
+            column: column.name_str().to_string(),
+            field: field_path(row, index),
+            expected,
+            found: sql_type_name(column),
+        },
+        None => ToqlMySqlAsyncError::DeserializeError {
+            index,
+            column: String::new(),
+            field: format!("column {}", index),
+            expected,
+            found: "no column".to_string(),
+        },
+    }
+}
+
+/// Type name without module paths, e.g. `Option<String>`.
+fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut segment = String::new();
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            segment.clear();
+        } else if c.is_alphanumeric() || c == '_' {
+            segment.push(c);
+        } else {
+            short.push_str(&segment);
+            segment.clear();
+            short.push(c);
+        }
+    }
+    short.push_str(&segment);
+    short
+}
+
+/// SQL type of a result column, e.g. `BIGINT UNSIGNED`.
+fn sql_type_name(column: &mysql_async::Column) -> String {
+    // Character set 63 is `binary`
+    let binary = column.character_set() == 63;
+    let flags = column.flags();
+    let name = match column.column_type() {
+        _ if flags.contains(ColumnFlags::ENUM_FLAG) => "ENUM",
+        _ if flags.contains(ColumnFlags::SET_FLAG) => "SET",
+        ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => "DECIMAL",
+        ColumnType::MYSQL_TYPE_TINY => "TINYINT",
+        ColumnType::MYSQL_TYPE_SHORT => "SMALLINT",
+        ColumnType::MYSQL_TYPE_INT24 => "MEDIUMINT",
+        ColumnType::MYSQL_TYPE_LONG => "INT",
+        ColumnType::MYSQL_TYPE_LONGLONG => "BIGINT",
+        ColumnType::MYSQL_TYPE_FLOAT => "FLOAT",
+        ColumnType::MYSQL_TYPE_DOUBLE => "DOUBLE",
+        ColumnType::MYSQL_TYPE_NULL => "NULL",
+        ColumnType::MYSQL_TYPE_TIMESTAMP | ColumnType::MYSQL_TYPE_TIMESTAMP2 => "TIMESTAMP",
+        ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE => "DATE",
+        ColumnType::MYSQL_TYPE_TIME | ColumnType::MYSQL_TYPE_TIME2 => "TIME",
+        ColumnType::MYSQL_TYPE_DATETIME | ColumnType::MYSQL_TYPE_DATETIME2 => "DATETIME",
+        ColumnType::MYSQL_TYPE_YEAR => "YEAR",
+        ColumnType::MYSQL_TYPE_BIT => "BIT",
+        ColumnType::MYSQL_TYPE_JSON => "JSON",
+        ColumnType::MYSQL_TYPE_ENUM => "ENUM",
+        ColumnType::MYSQL_TYPE_SET => "SET",
+        ColumnType::MYSQL_TYPE_GEOMETRY => "GEOMETRY",
+        ColumnType::MYSQL_TYPE_VARCHAR | ColumnType::MYSQL_TYPE_VAR_STRING if binary => {
+            "VARBINARY"
+        }
+        ColumnType::MYSQL_TYPE_VARCHAR | ColumnType::MYSQL_TYPE_VAR_STRING => "VARCHAR",
+        ColumnType::MYSQL_TYPE_STRING if binary => "BINARY",
+        ColumnType::MYSQL_TYPE_STRING => "CHAR",
+        ColumnType::MYSQL_TYPE_TINY_BLOB
+        | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+        | ColumnType::MYSQL_TYPE_LONG_BLOB
+        | ColumnType::MYSQL_TYPE_BLOB
+            if binary =>
+        {
+            "BLOB"
+        }
+        ColumnType::MYSQL_TYPE_TINY_BLOB
+        | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+        | ColumnType::MYSQL_TYPE_LONG_BLOB
+        | ColumnType::MYSQL_TYPE_BLOB => "TEXT",
+        t => return format!("{:?}", t),
+    };
+    if flags.contains(ColumnFlags::UNSIGNED_FLAG) {
+        format!("{} UNSIGNED", name)
+    } else {
+        name.to_string()
+    }
+}
+
+// Dates take their value with `next_date_value`, that applies the zero date policy
 macro_rules! from_row {
-        ($($type:ty),+) => {
+        ($next_value:ident; $($type:ty),+) => {
             $(
                impl toql::from_row::FromRow<Row, ToqlMySqlAsyncError> for $type {
                fn forward<'a, I>( iter: &mut I) -> Result<usize,ToqlMySqlAsyncError>
                 where
                         I: Iterator<Item = &'a Select>{
-                    if  iter.next().ok_or(
-                            toql::error::ToqlError::DeserializeError(
-                                toql::deserialize::error::DeserializeError::StreamEnd)
-                    )?.is_selected() {
-                        Ok(1)
-                    } else {
-                        Ok(0)
-                    }
+                    forward_column(iter)
                 }
                 // Return None, if unselected or column is null
                 fn from_row<'a, I>(
@@ -34,42 +485,871 @@ macro_rules! from_row {
                     where
                         I: Iterator<Item = &'a Select> + Clone,
                     {
-                        if iter
-                       // .inspect(|v| println!("Select is {:?}", v))
-                         . next().ok_or(
-                            toql::error::ToqlError::DeserializeError(
-                                toql::deserialize::error::DeserializeError::StreamEnd)
-                         )?.is_selected() {
-                            // First Option is None, if Index is out of bounds, second Option is Nullable column
-                             let v :Option<Result<Option<$type>, mysql_async::FromValueError>>  = row.0.get_opt(*i);
-                             let v = v.ok_or(toql::error::ToqlError::DeserializeError(
-                            toql::deserialize::error::DeserializeError::StreamEnd))?;
-
-                            let v = v.map_err(ToqlMySqlAsyncError::from)?;
-                            *i += 1;
-                            Ok(v)
-                        } else {
-                            Ok(None)
-                        }
+                        let index = *i;
+                        $next_value::<$type, _>(row, i, iter)?
+                            .map(|v| {
+                                mysql_async::from_value_opt::<$type>(v)
+                                    .map_err(|_| deserialize_error::<$type>(row, index))
+                            })
+                            .transpose()
                     }
                 }
 
             )+
         };
+        ($($type:ty),+) => {
+            from_row!(next_value; $($type),+);
+        };
         }
 
-from_row!(
-    NaiveDateTime,
-    String,
-    u8,
-    u16,
-    u32,
-    u64,
-    i8,
-    i16,
-    i32,
-    i64,
-    f32,
-    f64,
-    bool
-);
+/// Implement [FromRow] for a type, that is converted from the value of a single column.
+macro_rules! from_value_with {
+    ($next_value:ident; $type:ty, $convert:expr) => {
+        impl FromRow<Row, ToqlMySqlAsyncError> for $type {
+            fn forward<'a, I>(iter: &mut I) -> Result<usize, ToqlMySqlAsyncError>
+            where
+                I: Iterator<Item = &'a Select>,
+            {
+                forward_column(iter)
+            }
+
+            fn from_row<'a, I>(
+                row: &Row,
+                i: &mut usize,
+                iter: &mut I,
+            ) -> Result<Option<$type>, ToqlMySqlAsyncError>
+            where
+                I: Iterator<Item = &'a Select> + Clone,
+            {
+                let convert: fn(&Row, Value) -> Result<$type, ToqlMySqlAsyncError> = $convert;
+                let index = *i;
+                $next_value::<$type, _>(row, i, iter)?
+                    .map(|v| {
+                        convert(row, v).map_err(|e| match e {
+                            ToqlMySqlAsyncError::FromValueError(_) => {
+                                deserialize_error::<$type>(row, index)
+                            }
+                            e => e,
+                        })
+                    })
+                    .transpose()
+            }
+        }
+    };
+    ($type:ty, $convert:expr) => {
+        from_value_with!(next_value; $type, $convert);
+    };
+}
+
+/// Implement [FromRow] for a numeric type, that is converted according to the [NumericMode].
+macro_rules! from_number {
+    ($($type:ty),+) => {
+        $(
+            impl FromRow<Row, ToqlMySqlAsyncError> for $type {
+                fn forward<'a, I>(iter: &mut I) -> Result<usize, ToqlMySqlAsyncError>
+                where
+                    I: Iterator<Item = &'a Select>,
+                {
+                    forward_column(iter)
+                }
+
+                fn from_row<'a, I>(
+                    row: &Row,
+                    i: &mut usize,
+                    iter: &mut I,
+                ) -> Result<Option<$type>, ToqlMySqlAsyncError>
+                where
+                    I: Iterator<Item = &'a Select> + Clone,
+                {
+                    let index = *i;
+                    next_value::<$type, _>(row, i, iter)?
+                        .map(|v| number_from::<$type>(row, index, &bit_value(row, index, v)))
+                        .transpose()
+                }
+            }
+        )+
+    };
+}
+
+from_row!(NaiveTime, String, Vec<u8>);
+from_row!(next_date_value; NaiveDateTime, NaiveDate);
+
+impl FromRow<Row, ToqlMySqlAsyncError> for bool {
+    fn forward<'a, I>(iter: &mut I) -> Result<usize, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select>,
+    {
+        forward_column(iter)
+    }
+
+    fn from_row<'a, I>(row: &Row, i: &mut usize, iter: &mut I) -> Result<Option<bool>, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select> + Clone,
+    {
+        let index = *i;
+        next_value::<bool, _>(row, i, iter)?
+            .map(|v| {
+                mysql_async::from_value_opt::<bool>(bit_value(row, index, v))
+                    .map_err(|_| deserialize_error::<bool>(row, index))
+            })
+            .transpose()
+    }
+}
+
+from_number!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+/// Number of a column value.
+#[derive(Debug, Clone)]
+enum Number {
+    Int(i128),
+    Float(f32),
+    Double(f64),
+    /// Normalized text of a `DECIMAL` with fraction
+    Decimal(String),
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Number::Int(n) => write!(f, "{}", n),
+            Number::Float(n) => write!(f, "{}", n),
+            Number::Double(n) => write!(f, "{}", n),
+            Number::Decimal(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl Number {
+    fn from_value(column: Option<&mysql_async::Column>, v: &Value) -> Option<Number> {
+        match v {
+            Value::Int(n) => Some(Number::Int(i128::from(*n))),
+            Value::UInt(n) => Some(Number::Int(i128::from(*n))),
+            Value::Float(n) => Some(Number::Float(*n)),
+            Value::Double(n) => Some(Number::Double(*n)),
+            // Text protocol
+            Value::Bytes(b) => {
+                let s = std::str::from_utf8(b).ok()?.trim();
+                let column_type = column.map(|c| c.column_type());
+                if column_type == Some(ColumnType::MYSQL_TYPE_FLOAT) {
+                    s.parse().ok().map(Number::Float)
+                } else if column_type == Some(ColumnType::MYSQL_TYPE_DECIMAL)
+                    || column_type == Some(ColumnType::MYSQL_TYPE_NEWDECIMAL)
+                {
+                    // Decimals are sent as text with both protocols
+                    s.parse::<f64>().ok()?;
+                    let decimal = normalize_decimal(s);
+                    Some(match decimal.parse() {
+                        Ok(n) => Number::Int(n),
+                        Err(_) => Number::Decimal(decimal),
+                    })
+                } else {
+                    s.parse()
+                        .ok()
+                        .map(Number::Int)
+                        .or_else(|| s.parse().ok().map(Number::Double))
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Decimal text without plus sign, leading zeros and trailing zeros of the fraction,
+/// e.g. `-0012.50` becomes `-12.5`, so that it can be compared with a formatted float.
+fn normalize_decimal(text: &str) -> String {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (int, fract) = digits.split_once('.').unwrap_or((digits, ""));
+    let int = match int.trim_start_matches('0') {
+        "" => "0",
+        int => int,
+    };
+    let fract = fract.trim_end_matches('0');
+    let mut decimal = String::with_capacity(text.len());
+    if negative && (int != "0" || !fract.is_empty()) {
+        decimal.push('-');
+    }
+    decimal.push_str(int);
+    if !fract.is_empty() {
+        decimal.push('.');
+        decimal.push_str(fract);
+    }
+    decimal
+}
+
+/// Numeric field type.
+trait FromNumber: Sized + Display {
+    /// Nearest value of the type and true, if the conversion is exact.
+    fn from_number(n: Number) -> (Self, bool);
+}
+
+macro_rules! from_number_int {
+    ($($type:ty),+) => {
+        $(
+            impl FromNumber for $type {
+                fn from_number(n: Number) -> ($type, bool) {
+                    let d = match n {
+                        Number::Int(n) => {
+                            return match <$type>::try_from(n) {
+                                Ok(v) => (v, true),
+                                Err(_) if n < 0 => (<$type>::MIN, false),
+                                Err(_) => (<$type>::MAX, false),
+                            }
+                        }
+                        Number::Float(n) => f64::from(n),
+                        Number::Double(n) => n,
+                        Number::Decimal(n) => n.parse().unwrap_or(f64::NAN),
+                    };
+                    if d.fract() == 0.0 {
+                        if let Ok(v) = <$type>::try_from(d as i128) {
+                            return (v, true);
+                        }
+                    }
+                    // Float casts saturate, NaN becomes 0
+                    (d.round() as $type, false)
+                }
+            }
+        )+
+    };
+}
+
+from_number_int!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl FromNumber for f64 {
+    fn from_number(n: Number) -> (f64, bool) {
+        match n {
+            Number::Int(n) => {
+                let d = n as f64;
+                (d, d as i128 == n)
+            }
+            Number::Float(n) => (f64::from(n), true),
+            Number::Double(n) => (n, true),
+            // Exact, if the nearest double prints as the decimal
+            Number::Decimal(n) => {
+                let d = n.parse().unwrap_or(f64::NAN);
+                (d, normalize_decimal(&d.to_string()) == n)
+            }
+        }
+    }
+}
+
+impl FromNumber for f32 {
+    fn from_number(n: Number) -> (f32, bool) {
+        match n {
+            Number::Int(n) => {
+                let f = n as f32;
+                (f, f as i128 == n)
+            }
+            Number::Float(n) => (n, true),
+            Number::Double(n) => {
+                let f = n as f32;
+                if f.is_infinite() && n.is_finite() {
+                    (f32::MAX.copysign(f), false)
+                } else {
+                    (f, f64::from(f) == n || n.is_nan())
+                }
+            }
+            Number::Decimal(n) => {
+                let f = n.parse().unwrap_or(f32::NAN);
+                if f.is_infinite() {
+                    (f32::MAX.copysign(f), false)
+                } else {
+                    (f, normalize_decimal(&f.to_string()) == n)
+                }
+            }
+        }
+    }
+}
+
+/// Convert the value of the column at `index` according to the [NumericMode].
+fn number_from<T: FromNumber>(row: &Row, index: usize, v: &Value) -> Result<T, ToqlMySqlAsyncError> {
+    let column = row.0.columns_ref().get(index);
+    let n = Number::from_value(column, v).ok_or_else(|| deserialize_error::<T>(row, index))?;
+    let (value, exact) = T::from_number(n.clone());
+    if !exact {
+        let field = field_path(row, index);
+        let expected = short_type_name(std::any::type_name::<T>());
+        match row.1.numeric_mode {
+            NumericMode::Strict => {
+                return Err(ToqlMySqlAsyncError::NumericError {
+                    field,
+                    value: n.to_string(),
+                    expected,
+                })
+            }
+            NumericMode::Lenient => tracing::warn!(
+                "Value `{}` of field `{}` loaded as {} `{}`",
+                n,
+                field,
+                expected,
+                value
+            ),
+        }
+    }
+    Ok(value)
+}
+
+// Fixed size arrays, e.g. for hash columns
+impl<const N: usize> FromRow<Row, ToqlMySqlAsyncError> for [u8; N] {
+    fn forward<'a, I>(iter: &mut I) -> Result<usize, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select>,
+    {
+        <Vec<u8> as FromRow<Row, ToqlMySqlAsyncError>>::forward(iter)
+    }
+
+    fn from_row<'a, I>(
+        row: &Row,
+        i: &mut usize,
+        iter: &mut I,
+    ) -> Result<Option<[u8; N]>, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select> + Clone,
+    {
+        let index = *i;
+        <Vec<u8> as FromRow<Row, ToqlMySqlAsyncError>>::from_row(row, i, iter)?
+            .map(|v| <[u8; N]>::try_from(v).map_err(|_| deserialize_error::<[u8; N]>(row, index)))
+            .transpose()
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+from_row!(rust_decimal::Decimal);
+
+#[cfg(feature = "bigdecimal")]
+from_row!(bigdecimal::BigDecimal);
+
+/// Exact decimal for `DECIMAL` columns.
+///
+/// The wrapper is bound as string, so MySQL converts it without loss of precision.
+/// Toql has no argument conversion for decimal types, use this wrapper for fields,
+/// that are inserted or updated.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Numeric<T>(pub T);
+
+impl<T> Deref for Numeric<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Numeric<T> {
+    fn from(value: T) -> Self {
+        Numeric(value)
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl From<Numeric<rust_decimal::Decimal>> for SqlArg {
+    fn from(value: Numeric<rust_decimal::Decimal>) -> Self {
+        crate::sql_arg::decimal_arg(&value.0)
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl From<&Numeric<rust_decimal::Decimal>> for SqlArg {
+    fn from(value: &Numeric<rust_decimal::Decimal>) -> Self {
+        crate::sql_arg::decimal_arg(&value.0)
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+impl From<Numeric<bigdecimal::BigDecimal>> for SqlArg {
+    fn from(value: Numeric<bigdecimal::BigDecimal>) -> Self {
+        crate::sql_arg::bigdecimal_arg(&value.0)
+    }
+}
+
+#[cfg(feature = "bigdecimal")]
+impl From<&Numeric<bigdecimal::BigDecimal>> for SqlArg {
+    fn from(value: &Numeric<bigdecimal::BigDecimal>) -> Self {
+        crate::sql_arg::bigdecimal_arg(&value.0)
+    }
+}
+
+impl<T> FromRow<Row, ToqlMySqlAsyncError> for Numeric<T>
+where
+    T: FromRow<Row, ToqlMySqlAsyncError>,
+{
+    fn forward<'a, I>(iter: &mut I) -> Result<usize, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select>,
+    {
+        T::forward(iter)
+    }
+
+    fn from_row<'a, I>(
+        row: &Row,
+        i: &mut usize,
+        iter: &mut I,
+    ) -> Result<Option<Numeric<T>>, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select> + Clone,
+    {
+        Ok(T::from_row(row, i, iter)?.map(Numeric))
+    }
+}
+
+// `CHAR(36)` and `BINARY(16)` with the bytes in RFC 4122 order
+#[cfg(feature = "uuid")]
+from_value_with!(uuid::Uuid, |_, v| uuid_from(v, false));
+
+/// UUID in a `BINARY(16)` column, that is written with `UUID_TO_BIN(uuid, 1)`.
+///
+/// The swapped layout can't be told from the RFC 4122 order, so it's given by the wrapper.
+/// It loads swapped binary UUIDs and text UUIDs and binds swapped binary UUIDs.
+/// A plain `Uuid` loads `CHAR(36)` and `BINARY(16)` columns in RFC 4122 order.
+#[cfg(feature = "uuid")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SwappedUuid(pub uuid::Uuid);
+
+#[cfg(feature = "uuid")]
+impl Deref for SwappedUuid {
+    type Target = uuid::Uuid;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for SwappedUuid {
+    fn from(uuid: uuid::Uuid) -> Self {
+        SwappedUuid(uuid)
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<SwappedUuid> for SqlArg {
+    fn from(uuid: SwappedUuid) -> Self {
+        crate::sql_arg::swapped_uuid_arg(&uuid.0)
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<&SwappedUuid> for SqlArg {
+    fn from(uuid: &SwappedUuid) -> Self {
+        crate::sql_arg::swapped_uuid_arg(&uuid.0)
+    }
+}
+
+#[cfg(feature = "uuid")]
+from_value_with!(SwappedUuid, |_, v| uuid_from(v, true).map(SwappedUuid));
+
+#[cfg(feature = "uuid")]
+fn uuid_from(v: Value, swapped: bool) -> Result<uuid::Uuid, ToqlMySqlAsyncError> {
+    let uuid = match &v {
+        Value::Bytes(b) if b.len() == 16 => {
+            let mut bytes = [0u8; 16];
+            if swapped {
+                bytes[0..4].copy_from_slice(&b[4..8]);
+                bytes[4..6].copy_from_slice(&b[2..4]);
+                bytes[6..8].copy_from_slice(&b[0..2]);
+                bytes[8..16].copy_from_slice(&b[8..16]);
+            } else {
+                bytes.copy_from_slice(b);
+            }
+            Some(uuid::Uuid::from_bytes(bytes))
+        }
+        Value::Bytes(b) => std::str::from_utf8(b)
+            .ok()
+            .and_then(|s| uuid::Uuid::parse_str(s).ok()),
+        _ => None,
+    };
+    uuid.ok_or_else(|| mysql_async::FromValueError(v).into())
+}
+
+#[cfg(feature = "json")]
+from_value_with!(serde_json::Value, |_, v| json_from(v));
+
+/// Wrapper for fields in `JSON` columns.
+///
+/// The value is deserialized from the JSON document.
+/// It's converted into a [SqlArg] with `TryFrom` or [json_arg](crate::sql_arg::json_arg),
+/// that fail with [JsonError](ToqlMySqlAsyncError::JsonError), if the value can't be serialized.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Json<T>(pub T);
+
+#[cfg(feature = "json")]
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T> From<T> for Json<T> {
+    fn from(value: T) -> Self {
+        Json(value)
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: serde::Serialize> TryFrom<Json<T>> for SqlArg {
+    type Error = ToqlMySqlAsyncError;
+
+    fn try_from(value: Json<T>) -> Result<Self, Self::Error> {
+        crate::sql_arg::json_arg(&value.0)
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: serde::Serialize> TryFrom<&Json<T>> for SqlArg {
+    type Error = ToqlMySqlAsyncError;
+
+    fn try_from(value: &Json<T>) -> Result<Self, Self::Error> {
+        crate::sql_arg::json_arg(&value.0)
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T> FromRow<Row, ToqlMySqlAsyncError> for Json<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    fn forward<'a, I>(iter: &mut I) -> Result<usize, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select>,
+    {
+        forward_column(iter)
+    }
+
+    fn from_row<'a, I>(
+        row: &Row,
+        i: &mut usize,
+        iter: &mut I,
+    ) -> Result<Option<Json<T>>, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select> + Clone,
+    {
+        let index = *i;
+        next_value::<Json<T>, _>(row, i, iter)?
+            .map(|v| match &v {
+                Value::Bytes(b) => serde_json::from_slice(b)
+                    .map(Json)
+                    .map_err(|_| deserialize_error::<Json<T>>(row, index)),
+                _ => Err(deserialize_error::<Json<T>>(row, index)),
+            })
+            .transpose()
+    }
+}
+
+#[cfg(feature = "json")]
+fn json_from(v: Value) -> Result<serde_json::Value, ToqlMySqlAsyncError> {
+    match &v {
+        Value::Bytes(b) => serde_json::from_slice(b).ok(),
+        _ => None,
+    }
+    .ok_or_else(|| mysql_async::FromValueError(v).into())
+}
+
+/// Define a Rust enum for the members of an `ENUM` or `SET` column.
+///
+/// The macro implements [FromStr] and [Display] with the given member names,
+/// so the enum can be loaded and saved with [Enum] and `HashSet<T>`.
+/// Members are parsed case insensitive like MySQL compares them.
+///
+/// ```rust
+/// toql_mysql_async::sql_enum! {
+///     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///     pub enum Color {
+///         Red = "red",
+///         Green = "green",
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! sql_enum {
+    ($(#[$meta:meta])* $vis:vis enum $name:ident { $($variant:ident = $member:literal),+ $(,)? }) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant),+
+        }
+
+        impl ::std::str::FromStr for $name {
+            type Err = ::std::string::String;
+
+            fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+                $(
+                    if s.eq_ignore_ascii_case($member) {
+                        return Ok($name::$variant);
+                    }
+                )+
+                Err(s.to_string())
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(match self {
+                    $($name::$variant => $member),+
+                })
+            }
+        }
+    };
+}
+
+/// Wrapper for fields in `ENUM` columns.
+///
+/// The Rust enum is parsed with [FromStr] and saved with [Display],
+/// see [sql_enum](crate::sql_enum) to implement both.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Enum<T>(pub T);
+
+impl<T> Deref for Enum<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> From<T> for Enum<T> {
+    fn from(value: T) -> Self {
+        Enum(value)
+    }
+}
+
+impl<T: Display> From<Enum<T>> for SqlArg {
+    fn from(value: Enum<T>) -> Self {
+        SqlArg::Str(value.0.to_string())
+    }
+}
+
+impl<T: Display> From<&Enum<T>> for SqlArg {
+    fn from(value: &Enum<T>) -> Self {
+        SqlArg::Str(value.0.to_string())
+    }
+}
+
+impl<T: FromStr> FromRow<Row, ToqlMySqlAsyncError> for Enum<T> {
+    fn forward<'a, I>(iter: &mut I) -> Result<usize, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select>,
+    {
+        forward_column(iter)
+    }
+
+    fn from_row<'a, I>(
+        row: &Row,
+        i: &mut usize,
+        iter: &mut I,
+    ) -> Result<Option<Enum<T>>, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select> + Clone,
+    {
+        let index = *i;
+        next_value::<Enum<T>, _>(row, i, iter)?
+            .map(|v| {
+                let s = mysql_async::from_value_opt::<String>(v)
+                    .map_err(|_| deserialize_error::<Enum<T>>(row, index))?;
+                parse_member(row, index, &s).map(Enum)
+            })
+            .transpose()
+    }
+}
+
+// `SET` columns hold comma separated members
+impl<T> FromRow<Row, ToqlMySqlAsyncError> for HashSet<T>
+where
+    T: FromStr + Eq + Hash,
+{
+    fn forward<'a, I>(iter: &mut I) -> Result<usize, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select>,
+    {
+        forward_column(iter)
+    }
+
+    fn from_row<'a, I>(
+        row: &Row,
+        i: &mut usize,
+        iter: &mut I,
+    ) -> Result<Option<HashSet<T>>, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select> + Clone,
+    {
+        let index = *i;
+        next_value::<HashSet<T>, _>(row, i, iter)?
+            .map(|v| {
+                let s = mysql_async::from_value_opt::<String>(v)
+                    .map_err(|_| deserialize_error::<HashSet<T>>(row, index))?;
+                s.split(',')
+                    .filter(|m| !m.is_empty())
+                    .map(|m| parse_member(row, index, m))
+                    .collect()
+            })
+            .transpose()
+    }
+}
+
+/// Wrapper for `SET` columns, that are mapped to a bitflags type.
+///
+/// Every member of the set is a flag with the same name, compared case insensitive.
+/// The flags are saved with their names.
+#[cfg(feature = "bitflags")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SetFlags<T>(pub T);
+
+#[cfg(feature = "bitflags")]
+impl<T> Deref for SetFlags<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(feature = "bitflags")]
+impl<T> From<T> for SetFlags<T> {
+    fn from(value: T) -> Self {
+        SetFlags(value)
+    }
+}
+
+#[cfg(feature = "bitflags")]
+impl<T: bitflags::Flags> From<SetFlags<T>> for SqlArg {
+    fn from(value: SetFlags<T>) -> Self {
+        SqlArg::from(&value)
+    }
+}
+
+#[cfg(feature = "bitflags")]
+impl<T: bitflags::Flags> From<&SetFlags<T>> for SqlArg {
+    fn from(value: &SetFlags<T>) -> Self {
+        crate::sql_arg::set_arg(value.0.iter_names().map(|(name, _)| name))
+    }
+}
+
+#[cfg(feature = "bitflags")]
+impl<T: bitflags::Flags> FromRow<Row, ToqlMySqlAsyncError> for SetFlags<T> {
+    fn forward<'a, I>(iter: &mut I) -> Result<usize, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select>,
+    {
+        forward_column(iter)
+    }
+
+    fn from_row<'a, I>(
+        row: &Row,
+        i: &mut usize,
+        iter: &mut I,
+    ) -> Result<Option<SetFlags<T>>, ToqlMySqlAsyncError>
+    where
+        I: Iterator<Item = &'a Select> + Clone,
+    {
+        let index = *i;
+        next_value::<SetFlags<T>, _>(row, i, iter)?
+            .map(|v| {
+                let s = mysql_async::from_value_opt::<String>(v)
+                    .map_err(|_| deserialize_error::<SetFlags<T>>(row, index))?;
+                let mut flags = T::empty();
+                for member in s.split(',').filter(|m| !m.is_empty()) {
+                    let flag = T::FLAGS
+                        .iter()
+                        .find(|f| f.name().eq_ignore_ascii_case(member))
+                        .ok_or_else(|| ToqlMySqlAsyncError::EnumValueError {
+                            column: column_name(row, index),
+                            value: member.to_string(),
+                        })?;
+                    flags.insert(T::from_bits_retain(flag.value().bits()));
+                }
+                Ok(SetFlags(flags))
+            })
+            .transpose()
+    }
+}
+
+fn parse_member<T: FromStr>(row: &Row, index: usize, value: &str) -> Result<T, ToqlMySqlAsyncError> {
+    value
+        .parse()
+        .map_err(|_| ToqlMySqlAsyncError::EnumValueError {
+            column: column_name(row, index),
+            value: value.to_string(),
+        })
+}
+
+// Datetimes are returned in the session time zone
+from_value_with!(next_date_value; DateTime<FixedOffset>, |row, v| {
+    let naive = mysql_async::from_value_opt::<NaiveDateTime>(v)?;
+    Ok(local_datetime(&row.1.time_zone, &naive))
+});
+
+from_value_with!(next_date_value; DateTime<Utc>, |row, v| {
+    let naive = mysql_async::from_value_opt::<NaiveDateTime>(v)?;
+    Ok(local_datetime(&row.1.time_zone, &naive).with_timezone(&Utc))
+});
+
+// `TIME` columns hold durations between -838:59:59 and 838:59:59
+from_value_with!(Duration, |_, v| {
+    duration_from(&v).ok_or_else(|| mysql_async::FromValueError(v).into())
+});
+
+#[cfg(feature = "time")]
+from_row!(time::Time);
+
+#[cfg(feature = "time")]
+from_row!(next_date_value; time::Date, time::PrimitiveDateTime);
+
+#[cfg(feature = "time")]
+from_value_with!(next_date_value; time::OffsetDateTime, |row, v| {
+    let primitive = mysql_async::from_value_opt::<time::PrimitiveDateTime>(v)?;
+    let offset = time::UtcOffset::from_whole_seconds(row.1.time_zone.local_minus_utc())
+        .map_err(|e| mysql_async::FromValueError(Value::Bytes(e.to_string().into_bytes())))?;
+    Ok(primitive.assume_offset(offset))
+});
+
+fn local_datetime(time_zone: &FixedOffset, naive: &NaiveDateTime) -> DateTime<FixedOffset> {
+    // A fixed offset has always a single local time
+    time_zone.from_local_datetime(naive).unwrap()
+}
+
+fn duration_from(v: &Value) -> Option<Duration> {
+    let (negative, days, hours, minutes, seconds, micros) = match v {
+        Value::Time(negative, days, hours, minutes, seconds, micros) => (
+            *negative,
+            i64::from(*days),
+            i64::from(*hours),
+            i64::from(*minutes),
+            i64::from(*seconds),
+            i64::from(*micros),
+        ),
+        Value::Bytes(b) => {
+            // Text protocol: [-]hhh:mm:ss[.ffffff]
+            let s = std::str::from_utf8(b).ok()?;
+            let (negative, s) = match s.strip_prefix('-') {
+                Some(s) => (true, s),
+                None => (false, s),
+            };
+            let (s, fraction) = match s.split_once('.') {
+                Some((s, f)) if !f.is_empty() && f.len() <= 6 => {
+                    (s, f.parse::<i64>().ok()? * 10i64.pow(6 - f.len() as u32))
+                }
+                Some(_) => return None,
+                None => (s, 0),
+            };
+            let mut parts = s.split(':').map(|p| p.parse::<i64>().ok());
+            let hours = parts.next()??;
+            let minutes = parts.next()??;
+            let seconds = parts.next()??;
+            if parts.next().is_some() || minutes > 59 || seconds > 59 {
+                return None;
+            }
+            (negative, 0, hours, minutes, seconds, fraction)
+        }
+        _ => return None,
+    };
+    let d = Duration::days(days)
+        + Duration::hours(hours)
+        + Duration::minutes(minutes)
+        + Duration::seconds(seconds)
+        + Duration::microseconds(micros);
+    Some(if negative { -d } else { d })
+}
@@ -0,0 +1,128 @@
+//! Settings of the connection and pool wrappers.
+//!
+//! [MySqlAsync](crate::MySqlAsync) and [MySqlAsyncPool](crate::prelude::MySqlAsyncPool)
+//! hold their [Settings], a pool wrapper hands a copy to every connection it takes.
+//!
+//! ```rust
+//! let mut toql = MySqlAsyncPool::from(pool, &cache);
+//! toql.settings_mut()
+//!     .set_time_zone(FixedOffset::east_opt(3600).unwrap())
+//!     .set_numeric_mode(NumericMode::Lenient);
+//! ```
+
+use crate::{
+    backend::SchemaCache,
+    convert::Converter,
+    error::ErrorArgs,
+    paging::PageCountStrategy,
+    retry::RetryPolicy,
+    row::{NumericMode, RowSettings, ZeroDatePolicy},
+};
+use mysql_common::chrono::FixedOffset;
+use std::sync::Arc;
+
+/// Settings, that are shared by all connections of a pool.
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    pub(crate) page_count_strategy: PageCountStrategy,
+    pub(crate) schema: Arc<SchemaCache>,
+    pub(crate) row: Arc<RowSettings>,
+    pub(crate) retry_policy: Option<RetryPolicy>,
+    pub(crate) error_args: ErrorArgs,
+}
+
+impl Settings {
+    /// Set the strategy to count the records of a [Page::Counted](toql::prelude::Page::Counted).
+    pub fn set_page_count_strategy(&mut self, strategy: PageCountStrategy) -> &mut Self {
+        self.page_count_strategy = strategy;
+        self
+    }
+
+    pub fn page_count_strategy(&self) -> PageCountStrategy {
+        self.page_count_strategy
+    }
+
+    /// Set the time zone of the database session.
+    ///
+    /// `DATETIME` and `TIMESTAMP` values are loaded as local times of this zone into
+    /// `DateTime<Utc>` and `DateTime<FixedOffset>` fields. The default is UTC.
+    /// Pass it to [datetime_arg](crate::sql_arg::datetime_arg) to bind datetimes in this zone.
+    pub fn set_time_zone(&mut self, time_zone: FixedOffset) -> &mut Self {
+        Arc::make_mut(&mut self.row).time_zone = time_zone;
+        self
+    }
+
+    pub fn time_zone(&self) -> FixedOffset {
+        self.row.time_zone
+    }
+
+    /// Set how zero dates and invalid dates are loaded. The default is an error.
+    pub fn set_zero_date_policy(&mut self, policy: ZeroDatePolicy) -> &mut Self {
+        Arc::make_mut(&mut self.row).zero_date_policy = policy;
+        self
+    }
+
+    pub fn zero_date_policy(&self) -> ZeroDatePolicy {
+        self.row.zero_date_policy
+    }
+
+    /// Set how numbers are loaded into fields, that can't hold them exactly.
+    /// The default is [NumericMode::Strict].
+    pub fn set_numeric_mode(&mut self, mode: NumericMode) -> &mut Self {
+        Arc::make_mut(&mut self.row).numeric_mode = mode;
+        self
+    }
+
+    /// How numbers are loaded into fields, that can't hold them exactly,
+    /// see [set_numeric_mode](Self::set_numeric_mode).
+    pub fn numeric_mode(&self) -> NumericMode {
+        self.row.numeric_mode
+    }
+
+    /// Set the policy to retry transactions after a deadlock or a lock wait timeout.
+    /// Without policy transactions are not retried.
+    ///
+    /// The policy is applied by `retry_transaction` of [MySqlAsync](crate::MySqlAsync)
+    /// and [MySqlAsyncPool](crate::prelude::MySqlAsyncPool), `transaction` runs once.
+    pub fn set_retry_policy(&mut self, policy: Option<RetryPolicy>) -> &mut Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry_policy.as_ref()
+    }
+
+    /// Set which arguments of a failing statement are kept in the error.
+    /// By default no arguments are kept.
+    pub fn set_error_args(&mut self, error_args: ErrorArgs) -> &mut Self {
+        self.error_args = error_args;
+        self
+    }
+
+    pub fn error_args(&self) -> ErrorArgs {
+        self.error_args
+    }
+
+    /// Forget the looked up auto increment columns and field paths.
+    ///
+    /// Call it after a schema change, e.g. `ALTER TABLE` or `USE` of another database.
+    /// Unqualified tables are looked up in the default database of the connections.
+    /// The schema cache is shared with the connections of a pool and the transactions of a connection.
+    pub fn clear_schema_cache(&self) {
+        self.schema.clear();
+    }
+
+    /// Register a converter for a field of a Toql mapper, e.g. `("User", "active")`.
+    ///
+    /// The mapper is the name of the entity, the field is the Toql field name.
+    pub fn register_converter<T>(&mut self, mapper: &str, field: &str, converter: T) -> &mut Self
+    where
+        T: Converter + 'static,
+    {
+        Arc::make_mut(&mut self.row)
+            .converters
+            .insert(mapper, field, Arc::new(converter));
+        self
+    }
+}
@@ -1,6 +1,12 @@
 //! Conversion from [SqlArg] to MySQL query types.
+//!
+//! Binary data is bound as [Value::Bytes].
+//! Dates, times and durations are bound as strings, that MySQL converts on insert.
+//! Strings are bound unchanged, datetimes with an offset are converted into the time zone
+//! of the session only by [datetime_arg] and `time::offset_datetime_arg`.
 
 use mysql_async::Value;
+use mysql_common::chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveTime, TimeZone};
 use toql::sql_arg::SqlArg;
 
 pub fn values_from_ref(args: &[SqlArg]) -> Vec<Value> {
@@ -19,7 +25,190 @@ pub fn value_from(arg: SqlArg) -> Value {
         SqlArg::I64(d) => Value::from(d),
         SqlArg::F64(d) => Value::from(d),
         SqlArg::Str(d) => Value::from(d),
+        SqlArg::Bytes(d) => Value::Bytes(d),
         SqlArg::Bool(d) => Value::from(d),
         SqlArg::Null => Value::NULL,
     }
 }
+
+/// Argument for a `BINARY`, `VARBINARY` or `BLOB` column.
+pub fn bytes_arg(bytes: &[u8]) -> SqlArg {
+    SqlArg::Bytes(bytes.to_vec())
+}
+
+/// Argument for a `SET` column.
+pub fn set_arg<I>(members: I) -> SqlArg
+where
+    I: IntoIterator,
+    I::Item: std::fmt::Display,
+{
+    SqlArg::Str(
+        members
+            .into_iter()
+            .map(|m| m.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+    )
+}
+
+/// Argument for a `DATE` column.
+pub fn date_arg(date: &NaiveDate) -> SqlArg {
+    SqlArg::Str(date.format("%Y-%m-%d").to_string())
+}
+
+/// Argument for a `TIME` column, that holds a time of day.
+pub fn time_arg(time: &NaiveTime) -> SqlArg {
+    SqlArg::Str(time.format("%H:%M:%S%.6f").to_string())
+}
+
+/// Argument for a `DATETIME` or `TIMESTAMP` column.
+///
+/// The datetime is converted into the time zone of the session,
+/// see [Settings::time_zone](crate::settings::Settings::time_zone).
+pub fn datetime_arg<Tz: TimeZone>(datetime: &DateTime<Tz>, time_zone: &FixedOffset) -> SqlArg {
+    SqlArg::Str(
+        datetime
+            .with_timezone(time_zone)
+            .naive_local()
+            .format("%Y-%m-%d %H:%M:%S%.6f")
+            .to_string(),
+    )
+}
+
+/// Argument for a `TIME` column, that holds a duration.
+pub fn duration_arg(duration: &Duration) -> SqlArg {
+    // Out of range durations keep their sign and are rejected by MySQL
+    let micros = duration.num_microseconds().unwrap_or(if *duration < Duration::zero() {
+        i64::MIN
+    } else {
+        i64::MAX
+    });
+    let sign = if micros < 0 { "-" } else { "" };
+    let micros = micros.unsigned_abs();
+    let seconds = micros / 1_000_000;
+    SqlArg::Str(format!(
+        "{}{:02}:{:02}:{:02}.{:06}",
+        sign,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        micros % 1_000_000
+    ))
+}
+
+/// Argument for a `DECIMAL` column.
+///
+/// The decimal is bound as string, that MySQL converts without loss of precision.
+#[cfg(feature = "rust_decimal")]
+pub fn decimal_arg(decimal: &rust_decimal::Decimal) -> SqlArg {
+    SqlArg::Str(decimal.to_string())
+}
+
+/// Argument for a `DECIMAL` column.
+///
+/// The decimal is bound as string, that MySQL converts without loss of precision.
+#[cfg(feature = "bigdecimal")]
+pub fn bigdecimal_arg(decimal: &bigdecimal::BigDecimal) -> SqlArg {
+    SqlArg::Str(decimal.to_string())
+}
+
+/// Argument for a `BINARY(16)` column with the bytes in RFC 4122 order.
+#[cfg(feature = "uuid")]
+pub fn uuid_arg(uuid: &uuid::Uuid) -> SqlArg {
+    bytes_arg(uuid.as_bytes())
+}
+
+/// Argument for a `BINARY(16)` column, that is written with `UUID_TO_BIN(uuid, 1)`.
+///
+/// The time-low and time-high parts are swapped, so time based UUIDs are stored in ascending order.
+#[cfg(feature = "uuid")]
+pub fn swapped_uuid_arg(uuid: &uuid::Uuid) -> SqlArg {
+    let b = uuid.as_bytes();
+    let mut swapped = [0u8; 16];
+    swapped[0..2].copy_from_slice(&b[6..8]);
+    swapped[2..4].copy_from_slice(&b[4..6]);
+    swapped[4..8].copy_from_slice(&b[0..4]);
+    swapped[8..16].copy_from_slice(&b[8..16]);
+    bytes_arg(&swapped)
+}
+
+/// Argument for a `CHAR(36)` column.
+#[cfg(feature = "uuid")]
+pub fn uuid_text_arg(uuid: &uuid::Uuid) -> SqlArg {
+    SqlArg::Str(uuid.hyphenated().to_string())
+}
+
+/// Argument for a `JSON` column.
+///
+/// The value is serialized into JSON text, that MySQL parses into a JSON document.
+/// Serialization fails, if the `Serialize` implementation fails
+/// or a map has keys, that are no strings or numbers, e.g. sequences.
+#[cfg(feature = "json")]
+pub fn json_arg<T: serde::Serialize + ?Sized>(
+    value: &T,
+) -> Result<SqlArg, crate::error::ToqlMySqlAsyncError> {
+    Ok(SqlArg::Str(serde_json::to_string(value)?))
+}
+
+/// Arguments for the types of the `time` crate.
+#[cfg(feature = "time")]
+pub mod time {
+    use mysql_common::chrono::FixedOffset;
+    use toql::sql_arg::SqlArg;
+
+    /// Argument for a `DATE` column.
+    pub fn date_arg(date: &::time::Date) -> SqlArg {
+        SqlArg::Str(format_date(date))
+    }
+
+    /// Argument for a `TIME` column.
+    pub fn time_arg(time: &::time::Time) -> SqlArg {
+        SqlArg::Str(format_time(time))
+    }
+
+    /// Argument for a `DATETIME` or `TIMESTAMP` column.
+    pub fn primitive_datetime_arg(datetime: &::time::PrimitiveDateTime) -> SqlArg {
+        SqlArg::Str(format!(
+            "{} {}",
+            format_date(&datetime.date()),
+            format_time(&datetime.time())
+        ))
+    }
+
+    /// Argument for a `DATETIME` or `TIMESTAMP` column.
+    ///
+    /// The datetime is converted into the time zone of the session,
+    /// see [Settings::time_zone](crate::settings::Settings::time_zone).
+    pub fn offset_datetime_arg(
+        datetime: &::time::OffsetDateTime,
+        time_zone: &FixedOffset,
+    ) -> SqlArg {
+        // A chrono offset is always within the range of a time offset
+        let offset = ::time::UtcOffset::from_whole_seconds(time_zone.local_minus_utc()).unwrap();
+        let local = datetime.to_offset(offset);
+        SqlArg::Str(format!(
+            "{} {}",
+            format_date(&local.date()),
+            format_time(&local.time())
+        ))
+    }
+
+    fn format_date(date: &::time::Date) -> String {
+        format!(
+            "{:04}-{:02}-{:02}",
+            date.year(),
+            u8::from(date.month()),
+            date.day()
+        )
+    }
+
+    fn format_time(time: &::time::Time) -> String {
+        format!(
+            "{:02}:{:02}:{:02}.{:06}",
+            time.hour(),
+            time.minute(),
+            time.second(),
+            time.microsecond()
+        )
+    }
+}
@@ -0,0 +1,123 @@
+//! Load entities as a stream.
+//!
+//! Unlike [load_many](toql::toql_api::ToqlApi::load_many) the result set is not collected.
+//! Rows are read from the server on demand and deserialized one by one,
+//! so large exports run in constant memory.
+//!
+//! ```rust
+//! use futures_util::TryStreamExt;
+//!
+//! let mut payments = toql.load_stream(query!(Payment, "*")).await?;
+//! while let Some(payment) = payments.try_next().await? {
+//!     // ...
+//! }
+//! ```
+use crate::{
+    backend::field_paths, error::ToqlMySqlAsyncError, queryable::Queryable, result::Result,
+    row::Row, MySqlAsync,
+};
+use futures_core::stream::BoxStream;
+use futures_util::{future, StreamExt, TryStreamExt};
+use std::borrow::Borrow;
+use toql::{
+    backend::map,
+    error::ToqlError,
+    prelude::{log_sql, FromRow, Sql},
+    query::Query,
+    sql_builder::SqlBuilder,
+    table_mapper::mapped::Mapped,
+    toql_api::load::Load,
+};
+
+impl<'a, C> MySqlAsync<'a, C>
+where
+    C: Queryable + Send,
+{
+    /// Load entities for a Toql query as a stream.
+    ///
+    /// Only the top entity and its joins are loaded, because merges would need
+    /// further queries for every entity. A query that selects a merge path
+    /// is rejected with [StreamMergeError](ToqlMySqlAsyncError::StreamMergeError).
+    #[tracing::instrument(skip(self, query), fields(ty = %<T as Mapped>::type_name()))]
+    pub async fn load_stream<'s, T, B>(
+        &'s mut self,
+        query: B,
+    ) -> Result<BoxStream<'s, Result<T>>>
+    where
+        T: Load<Row, ToqlMySqlAsyncError> + Send + 's,
+        B: Borrow<Query<T>> + Send + Sync,
+    {
+        let ty = <T as Mapped>::type_name();
+        {
+            let mut registry = self
+                .backend
+                .cache
+                .registry
+                .write()
+                .map_err(ToqlError::from)?;
+            map::map::<T>(&mut registry)?;
+        }
+
+        let converters = &self.backend.settings.row.converters;
+        let converted = converters.convert_query(&ty, query.borrow());
+        let result = {
+            let registry = self
+                .backend
+                .cache
+                .registry
+                .read()
+                .map_err(ToqlError::from)?;
+            converters.resolve(&registry, &self.backend.context.roles);
+            let mut builder = SqlBuilder::new(&ty, &*registry)
+                .with_aux_params(self.backend.context.aux_params.clone())
+                .with_roles(self.backend.context.roles.clone());
+            builder.build_select("", converted.as_ref().unwrap_or(query.borrow()))?
+        };
+        if let Some(path) = result.unmerged_home_paths().iter().next() {
+            return Err(ToqlMySqlAsyncError::StreamMergeError(path.to_string()));
+        }
+
+        let sql = result.to_sql(&self.backend.context.alias_format);
+        log_sql!(&sql);
+        let Sql(sql_stmt, args) = sql;
+        let args = crate::sql_arg::values_from(args);
+        let row_settings = self.backend.settings.row.clone();
+        let args_record = self.backend.settings.error_args.record(&args);
+        let rows = match self.backend.conn.exec_stream(sql_stmt.as_str(), args).await {
+            Ok(rows) => rows,
+            Err(err) => {
+                return Err(ToqlMySqlAsyncError::statement(err, &sql_stmt, args_record)
+                    .with_entity("load", <T as Mapped>::type_name()))
+            }
+        };
+
+        // Field paths are resolved with the columns of the first row
+        let cache = self.backend.cache;
+        let roles = self.backend.context.roles.clone();
+        let alias_format = self.backend.context.alias_format.clone();
+        let schema = self.backend.settings.schema.clone();
+        let mut paths = None;
+        let entities = rows
+            .map(move |row| {
+                let row = row?;
+                if paths.is_none() {
+                    let registry = cache.registry.read().map_err(ToqlError::from)?;
+                    paths = Some(field_paths(
+                        &registry,
+                        &roles,
+                        &alias_format,
+                        &schema,
+                        &ty,
+                        row.columns_ref(),
+                    )?);
+                }
+                let row = Row(row, row_settings.clone(), paths.clone());
+                let mut iter = result.select_stream().iter();
+                let mut i = 0;
+                <T as FromRow<Row, ToqlMySqlAsyncError>>::from_row(&row, &mut i, &mut iter)
+            })
+            .try_filter_map(|e| future::ready(Ok(e)));
+
+        Ok(Box::pin(entities))
+    }
+}
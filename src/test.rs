@@ -1,8 +1,28 @@
-use crate::prelude::{MySqlAsync, ToqlMySqlAsyncError};
+use crate::backend::MySqlAsyncBackend;
+use crate::settings::Settings;
+use crate::convert::YesNo;
+use crate::error::ErrorArgs;
+use crate::insert::{count_placeholders, InsertStatement};
+use crate::keyset::{keyset_sql, Cursor};
+use crate::mock::{MockConn, MockResult};
+use crate::paging::{unlimited_count_sql, PageCountStrategy};
+use crate::prelude::{MySqlAsync, MySqlAsyncPool, Row, ToqlMySqlAsyncError};
+use crate::retry::RetryPolicy;
+use crate::row::{is_invalid_date, Numeric, NumericMode, RowSettings, ZeroDatePolicy};
+use crate::sql_arg::{bytes_arg, datetime_arg, duration_arg, value_from};
+use crate::test_server::{Reply, ResultSet, TestServer};
+use futures_util::TryStreamExt;
+use mysql_async::consts::ColumnType;
 use mysql_async::prelude::*;
-use mysql_async::TxOpts;
+use mysql_async::{Column, Opts, OptsBuilder, Pool, PoolConstraints, PoolOpts, TxOpts, Value};
+use mysql_common::chrono::{Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::collections::HashSet;
+use std::sync::Arc;
+use toql::backend::Backend;
+use toql::from_row::FromRow;
 use toql::prelude::ToqlApi;
-use toql::prelude::{paths, query, Cache, Toql};
+use toql::prelude::{fields, paths, query, Cache, Context, Page, Sql, SqlArg, Toql};
+use toql::sql_builder::select_stream::Select;
 
 #[derive(Debug, PartialEq, Eq, Clone, Toql)]
 struct Payment {
@@ -151,3 +171,1677 @@ async fn demo_transaction() -> Result<(), ToqlMySqlAsyncError> {
     // the async fn returns Result, so
     Ok(())
 }
+
+#[test]
+fn keyset_cursor_roundtrip() {
+    let cursor: Cursor = r"u12,i-3,sa\,b\\c,x00ff,btrue,n".parse().unwrap();
+    assert_eq!(cursor.to_string(), r"u12,i-3,sa\,b\\c,x00ff,btrue,n");
+    assert_eq!(cursor.to_string().parse::<Cursor>().unwrap(), cursor);
+    assert!("x1".parse::<Cursor>().is_err());
+    assert!("q1".parse::<Cursor>().is_err());
+}
+
+#[test]
+fn keyset_sql_seeks_after_cursor() {
+    let sql = Sql(
+        "SELECT payment.customer_id, payment.amount FROM Payment payment \
+         WHERE payment.amount > ? ORDER BY payment.amount DESC, payment.customer_id ASC LIMIT 0, 2"
+            .to_string(),
+        vec![SqlArg::I64(0)],
+    );
+    let cursor: Cursor = "i9,i5".parse().unwrap();
+    let (Sql(keyset_sql, args), columns) = keyset_sql(sql, Some(&cursor)).unwrap();
+    assert_eq!(columns, 2);
+    assert_eq!(
+        keyset_sql,
+        "SELECT payment.amount AS toql_cursor_0, payment.customer_id AS toql_cursor_1, \
+         payment.customer_id, payment.amount FROM Payment payment \
+         WHERE (payment.amount > ?) \
+         AND ((payment.amount < ?) OR (payment.amount = ? AND payment.customer_id > ?)) \
+         ORDER BY payment.amount DESC, payment.customer_id ASC LIMIT 0, 2"
+    );
+    assert_eq!(
+        args,
+        vec![
+            SqlArg::I64(0),
+            SqlArg::I64(9),
+            SqlArg::I64(9),
+            SqlArg::I64(5)
+        ]
+    );
+
+    let short: Cursor = "i9".parse().unwrap();
+    let sql = Sql(
+        "SELECT payment.amount FROM Payment payment ORDER BY payment.amount, payment.customer_id"
+            .to_string(),
+        vec![],
+    );
+    assert!(keyset_sql(sql, Some(&short)).is_err());
+}
+
+#[test]
+fn insert_statement_parse() {
+    let sql = "INSERT INTO `db`.`Payment` (`customer_id`, amount, note) \
+               VALUES (?, ?, 'a,(b)?'), (?, ?, ?) ON DUPLICATE KEY UPDATE amount = ?";
+    let insert = InsertStatement::parse(sql).unwrap();
+    assert_eq!(insert.table, "db.Payment");
+    assert_eq!(insert.columns, vec!["customer_id", "amount", "note"]);
+    assert_eq!(insert.rows, vec!["(?, ?, 'a,(b)?')", "(?, ?, ?)"]);
+    assert_eq!(insert.tail_placeholders(), 1);
+    assert_eq!(
+        insert.row_sql(insert.rows[1]),
+        "INSERT INTO `db`.`Payment` (`customer_id`, amount, note) \
+         VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE amount = ?"
+    );
+    assert_eq!(count_placeholders(sql), 6);
+
+    assert!(InsertStatement::parse("SELECT id FROM Payment WHERE note = 'INSERT INTO'").is_none());
+    assert!(InsertStatement::parse(
+        "INSERT INTO Payment (id) SELECT id FROM Old ON DUPLICATE KEY UPDATE id = VALUES(id)"
+    )
+    .is_none());
+}
+
+#[test]
+fn bytes_arg_binds_bytes() {
+    let bytes = vec![0u8, 1, 0xfe, 0xff];
+    assert_eq!(value_from(bytes_arg(&bytes)), Value::Bytes(bytes));
+    assert_eq!(
+        value_from(toql::prelude::SqlArg::Str("foo".into())),
+        Value::Bytes(b"foo".to_vec())
+    );
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Toql)]
+struct Blob {
+    #[toql(key)]
+    id: u64,
+    data: Vec<u8>,
+    note: Option<Vec<u8>>,
+    hash: [u8; 4],
+}
+
+#[tokio::test]
+async fn mock_bytes_roundtrip() -> Result<(), ToqlMySqlAsyncError> {
+    let mut blob = Blob {
+        id: 1,
+        data: vec![0, 1, 0xfe, 0xff],
+        note: Some(b"foo".to_vec()),
+        hash: [0xde, 0xad, 0xbe, 0xef],
+    };
+    let mut conn = MockConn::new();
+    conn.push_rows(&["Field", "Type"], vec![]) // No auto increment column
+        .push_ok(1, None)
+        .push_rows(
+            &["id", "data", "note", "hash"],
+            vec![vec![
+                Value::UInt(1),
+                Value::Bytes(blob.data.clone()),
+                Value::Bytes(b"foo".to_vec()),
+                Value::Bytes(blob.hash.to_vec()),
+            ]],
+        );
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    toql.insert_one(&mut blob, paths!(top)).await?;
+    let loaded = toql.load_one(query!(Blob, "id eq 1, *")).await?;
+    assert_eq!(loaded, blob);
+
+    let statements = toql.conn().take_statements();
+    assert!(statements[1].sql.starts_with("INSERT INTO Blob"));
+    assert_eq!(
+        statements[1].params,
+        vec![
+            Value::UInt(1),
+            Value::Bytes(vec![0, 1, 0xfe, 0xff]),
+            Value::Bytes(b"foo".to_vec()),
+            Value::Bytes(vec![0xde, 0xad, 0xbe, 0xef]),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn chrono_args() {
+    let duration = -(Duration::hours(100) + Duration::seconds(3) + Duration::milliseconds(500));
+    assert_eq!(
+        duration_arg(&duration),
+        toql::prelude::SqlArg::Str("-100:00:03.500000".into())
+    );
+    let out_of_range = Duration::milliseconds(1 << 60);
+    for (duration, sign) in vec![(out_of_range, false), (-out_of_range, true)] {
+        match duration_arg(&duration) {
+            SqlArg::Str(s) => assert_eq!(s.starts_with('-'), sign, "{}", s),
+            arg => panic!("Expected string, got {:?}", arg),
+        }
+    }
+
+    let datetime = Utc.with_ymd_and_hms(2022, 3, 21, 23, 30, 0).unwrap();
+    assert_eq!(
+        datetime_arg(&datetime, &FixedOffset::east_opt(2 * 3600).unwrap()),
+        toql::prelude::SqlArg::Str("2022-03-22 01:30:00.000000".into())
+    );
+}
+
+#[tokio::test]
+async fn mock_insert_datetime_in_time_zone() -> Result<(), ToqlMySqlAsyncError> {
+    let mut conn = MockConn::new();
+    conn.push_rows(&["Field", "Type"], vec![]) // No auto increment column
+        .push_ok(1, None);
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    toql.settings_mut().set_time_zone(FixedOffset::west_opt(5 * 3600).unwrap());
+    let at = Utc.with_ymd_and_hms(2022, 3, 21, 23, 30, 0).unwrap();
+    let sql = Sql(
+        "INSERT INTO Event (id, at) VALUES (?, ?)".to_string(),
+        vec![SqlArg::U64(1), datetime_arg(&at, &toql.settings().time_zone())],
+    );
+    toql.backend.insert_sql(sql).await?;
+
+    let statements = toql.conn().take_statements();
+    assert_eq!(
+        statements[1].params,
+        vec![Value::UInt(1), Value::Bytes(b"2022-03-21 18:30:00.000000".to_vec())]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_datetime_text_unchanged() -> Result<(), ToqlMySqlAsyncError> {
+    let text = "2024-01-01T00:00:00+02:00";
+    let mut payment = Payment {
+        customer_id: 1,
+        amount: 2,
+        account_name: Some(text.into()),
+    };
+    let mut conn = MockConn::new();
+    conn.push_rows(&["Field", "Type"], vec![]) // No auto increment column
+        .push_ok(1, None)
+        .push_ok(1, None);
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    toql.settings_mut().set_time_zone(FixedOffset::west_opt(5 * 3600).unwrap());
+    toql.insert_one(&mut payment, paths!(top)).await?;
+    payment.account_name = Some("2024-01-01 00:00:00 UTC".into());
+    toql.update_one(&mut payment, fields!(top)).await?;
+
+    // Strings, that look like datetimes, are bound as they are
+    let statements = toql.conn().take_statements();
+    assert!(statements[1].sql.starts_with("INSERT INTO Payment"));
+    assert!(statements[1]
+        .params
+        .contains(&Value::Bytes(text.as_bytes().to_vec())));
+    assert!(statements[2].sql.starts_with("UPDATE Payment"));
+    assert!(statements[2]
+        .params
+        .contains(&Value::Bytes(b"2024-01-01 00:00:00 UTC".to_vec())));
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Toql)]
+struct Member {
+    #[toql(key)]
+    id: u64,
+    active: bool,
+}
+
+#[tokio::test]
+async fn mock_converter_roundtrip() -> Result<(), ToqlMySqlAsyncError> {
+    let columns: Arc<[Column]> = ["id", "active"]
+        .iter()
+        .map(|c| {
+            Column::new(ColumnType::MYSQL_TYPE_VAR_STRING)
+                .with_org_table(b"Member")
+                .with_org_name(c.as_bytes())
+                .with_name(c.as_bytes())
+        })
+        .collect::<Vec<_>>()
+        .into();
+    let mut conn = MockConn::new();
+    conn.push_result(MockResult::Rows(vec![mysql_common::row::new_row(
+        vec![Value::UInt(1), Value::Bytes(b"Y".to_vec())],
+        columns,
+    )]))
+    .push_rows(&["Field", "Type"], vec![]) // No auto increment column
+    .push_ok(1, None)
+    .push_ok(1, None);
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    toql.settings_mut().register_converter("Member", "active", YesNo);
+    let loaded = toql
+        .load_many(query!(Member, "id, active eq ?", true))
+        .await?;
+    assert_eq!(loaded, vec![Member { id: 1, active: true }]);
+
+    let mut member = Member {
+        id: 2,
+        active: false,
+    };
+    toql.insert_one(&mut member, paths!(top)).await?;
+    member.active = true;
+    toql.update_one(&mut member, fields!(top)).await?;
+
+    let statements = toql.conn().take_statements();
+    assert_eq!(statements.len(), 4);
+    assert_eq!(statements[0].params, vec![Value::Bytes(b"Y".to_vec())]);
+    assert!(statements[2].sql.starts_with("INSERT INTO Member"));
+    assert_eq!(
+        statements[2].params,
+        vec![Value::UInt(2), Value::Bytes(b"N".to_vec())]
+    );
+    assert!(statements[3].sql.starts_with("UPDATE Member"));
+    assert_eq!(
+        statements[3].params,
+        vec![Value::Bytes(b"Y".to_vec()), Value::UInt(2)]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_insert_without_auto_increment() -> Result<(), ToqlMySqlAsyncError> {
+    let mut payments = vec![
+        Payment {
+            customer_id: 1,
+            amount: 2,
+            account_name: None,
+        },
+        Payment {
+            customer_id: 3,
+            amount: 4,
+            account_name: Some("foo".into()),
+        },
+    ];
+
+    let mut conn = MockConn::new();
+    conn.push_rows(&["Field", "Type"], vec![]) // No auto increment column
+        .push_ok(2, None);
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    toql.insert_many(&mut payments, paths!(top)).await?;
+
+    let statements = toql.conn().take_statements();
+    assert_eq!(statements.len(), 2);
+    assert!(statements[0].sql.starts_with("SHOW COLUMNS FROM `Payment`"));
+    assert!(statements[1].sql.starts_with("INSERT INTO Payment"));
+    assert_eq!(statements[1].params.len(), 6);
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_schema_cache_shared() -> Result<(), ToqlMySqlAsyncError> {
+    let payment = || Payment {
+        customer_id: 1,
+        amount: 2,
+        account_name: None,
+    };
+    let cache = Cache::default();
+
+    let mut conn = MockConn::new();
+    conn.push_rows(&["Field", "Type"], vec![]) // No auto increment column
+        .push_ok(2, None);
+    let mut toql = MySqlAsync::from(conn, &cache);
+    toql.insert_many(&mut vec![payment(), payment()], paths!(top))
+        .await?;
+    assert_eq!(toql.conn().take_statements().len(), 2);
+
+    // Wrapper with the same settings, like the connections of a pool, doesn't ask the server again
+    let mut conn = MockConn::new();
+    conn.push_ok(2, None);
+    let settings = toql.settings().clone();
+    let mut shared = MySqlAsync::with_settings(conn, &cache, Context::default(), settings);
+    shared
+        .insert_many(&mut vec![payment(), payment()], paths!(top))
+        .await?;
+    let statements = shared.conn().take_statements();
+    assert_eq!(statements.len(), 1);
+    assert!(statements[0].sql.starts_with("INSERT INTO Payment"));
+
+    // Schema change
+    toql.settings().clear_schema_cache();
+    shared
+        .conn()
+        .push_rows(&["Field", "Type"], vec![])
+        .push_ok(2, None);
+    shared
+        .insert_many(&mut vec![payment(), payment()], paths!(top))
+        .await?;
+    let statements = shared.conn().take_statements();
+    assert_eq!(statements.len(), 2);
+    assert!(statements[0].sql.starts_with("SHOW COLUMNS FROM `Payment`"));
+
+    // Wrapper on the same Toql cache with own settings asks again
+    let mut conn = MockConn::new();
+    conn.push_rows(&["Field", "Type"], vec![]).push_ok(2, None);
+    let mut other = MySqlAsync::from(conn, &cache);
+    other
+        .insert_many(&mut vec![payment(), payment()], paths!(top))
+        .await?;
+    assert_eq!(other.conn().take_statements().len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_count() -> Result<(), ToqlMySqlAsyncError> {
+    let mut conn = MockConn::new();
+    conn.push_rows(&["count"], vec![vec![Value::UInt(42)]]);
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    let count = toql.count(query!(Payment, "*")).await?;
+
+    assert_eq!(count, 42);
+    assert!(toql.conn().statements()[0].sql.starts_with("SELECT COUNT"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_counted_page() -> Result<(), ToqlMySqlAsyncError> {
+    let columns = ["customer_id", "amount", "account_name"];
+    let row = vec![Value::Int(1), Value::Int(2), Value::NULL];
+
+    let mut counts = Vec::new();
+    for &strategy in &[
+        PageCountStrategy::CountQuery,
+        PageCountStrategy::WindowCount,
+        PageCountStrategy::FoundRows,
+    ] {
+        let mut conn = MockConn::new();
+        if strategy == PageCountStrategy::WindowCount {
+            let mut window_row = vec![Value::UInt(5)];
+            window_row.extend(row.clone());
+            conn.push_rows(
+                &["toql_page_count", "customer_id", "amount", "account_name"],
+                vec![window_row],
+            );
+        } else {
+            conn.push_rows(&columns, vec![row.clone()])
+                .push_rows(&["count"], vec![vec![Value::UInt(5)]]);
+        }
+        conn.push_rows(&["count"], vec![vec![Value::UInt(7)]]);
+
+        let cache = Cache::default();
+        let mut toql = MySqlAsync::from(conn, &cache);
+        toql.settings_mut().set_page_count_strategy(strategy);
+        let (payments, page_counts) = toql
+            .load_page(query!(Payment, "*"), Page::Counted(0, 1))
+            .await?;
+
+        assert_eq!(
+            payments,
+            vec![Payment {
+                customer_id: 1,
+                amount: 2,
+                account_name: None,
+            }]
+        );
+        let page_counts = page_counts.unwrap();
+        counts.push((page_counts.filtered, page_counts.total));
+
+        let statements = toql.conn().take_statements();
+        match strategy {
+            PageCountStrategy::CountQuery => {
+                assert_eq!(statements.len(), 3);
+                assert!(statements[1].sql.starts_with("SELECT COUNT(*) FROM (SELECT 1 FROM "));
+            }
+            PageCountStrategy::WindowCount => {
+                assert_eq!(statements.len(), 2);
+                assert!(statements[0].sql.contains("COUNT(*) OVER() AS toql_page_count"));
+            }
+            PageCountStrategy::FoundRows => {
+                assert_eq!(statements.len(), 3);
+                assert!(statements[0].sql.contains("SQL_CALC_FOUND_ROWS"));
+                assert_eq!(statements[1].sql, "SELECT FOUND_ROWS()");
+            }
+        }
+    }
+    assert_eq!(counts, vec![(5, 7); 3]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_failed_counted_page() -> Result<(), ToqlMySqlAsyncError> {
+    let mut conn = MockConn::new();
+    conn.push_server_error(1317, "70100", "Query execution was interrupted")
+        .push_rows(
+            &["customer_id", "amount", "account_name"],
+            vec![vec![Value::Int(1), Value::Int(2), Value::NULL]],
+        );
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    toql.settings_mut().set_page_count_strategy(PageCountStrategy::WindowCount);
+    assert!(toql
+        .load_page(query!(Payment, "*"), Page::Counted(0, 1))
+        .await
+        .is_err());
+
+    // Next select has no window count column
+    let payments = toql.load_many(query!(Payment, "*")).await?;
+    assert_eq!(
+        payments,
+        vec![Payment {
+            customer_id: 1,
+            amount: 2,
+            account_name: None,
+        }]
+    );
+    Ok(())
+}
+
+#[test]
+fn unlimited_count_sql_replaces_columns() {
+    let sql = Sql(
+        "SELECT user.id, role.id, NULL, (SELECT COUNT(*) FROM Log log WHERE log.level > ?) \
+         FROM User user JOIN Role role ON (role.user_id = user.id) \
+         WHERE user.name = 'ORDER LIMIT' AND user.id > ? ORDER BY user.id ASC LIMIT 20, 10"
+            .to_string(),
+        vec![SqlArg::U64(3), SqlArg::U64(1)],
+    );
+    let Sql(count_sql, args) = unlimited_count_sql(sql);
+    assert_eq!(
+        count_sql,
+        "SELECT COUNT(*) FROM (SELECT 1 FROM User user JOIN Role role ON (role.user_id = user.id) \
+         WHERE user.name = 'ORDER LIMIT' AND user.id > ?) AS toql_page"
+    );
+    assert_eq!(args, vec![SqlArg::U64(1)]);
+
+    let sql = Sql(
+        "SELECT DISTINCT user.id, role.id AS role_id FROM User user \
+         JOIN Role role ON (role.user_id = user.id) LIMIT 0, 10"
+            .to_string(),
+        vec![],
+    );
+    let Sql(count_sql, _) = unlimited_count_sql(sql);
+    assert_eq!(
+        count_sql,
+        "SELECT COUNT(*) FROM (SELECT DISTINCT user.id AS c0, role.id AS c1 FROM User user \
+         JOIN Role role ON (role.user_id = user.id)) AS toql_page"
+    );
+}
+
+#[tokio::test]
+async fn test_server_load() -> Result<(), ToqlMySqlAsyncError> {
+    let server = TestServer::start().await.unwrap();
+    server.push(Reply::ResultSet(ResultSet::new(
+        &["customer_id", "amount", "account_name"],
+        vec![
+            vec![Value::Int(1), Value::Int(2), Value::NULL],
+            vec![Value::Int(3), Value::Int(4), Value::Bytes(b"foo".to_vec())],
+        ],
+    )));
+
+    let pool = mysql_async::Pool::new(server.url().as_str());
+    let conn = pool.get_conn().await?;
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    let payments = toql.load_many(query!(Payment, "customer_id eq 3")).await?;
+
+    assert_eq!(payments.len(), 2);
+    assert_eq!(payments[1].account_name, Some("foo".to_string()));
+    let statements = server.statements();
+    assert!(statements[0].prepared);
+    assert!(statements[0].sql.starts_with("SELECT"));
+    assert_eq!(statements[0].params.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_deserialize_error() -> Result<(), ToqlMySqlAsyncError> {
+    let server = TestServer::start().await.unwrap();
+    let column = |name: &str, column_type| {
+        Column::new(column_type)
+            .with_name(name.as_bytes())
+            .with_org_name(name.as_bytes())
+            .with_org_table(b"Payment")
+    };
+    server.push(Reply::ResultSet(ResultSet::with_columns(
+        vec![
+            column("customer_id", ColumnType::MYSQL_TYPE_LONG),
+            column("amount", ColumnType::MYSQL_TYPE_LONG),
+            column("account_name", ColumnType::MYSQL_TYPE_LONG),
+        ],
+        vec![vec![Value::Int(1), Value::Int(2), Value::Int(3)]],
+    )));
+
+    let conn = mysql_async::Conn::from_url(server.url()).await?;
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    let err = toql
+        .load_many(query!(Payment, "*"))
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Payment.account_name: expected String, got INT"
+    );
+    match err {
+        ToqlMySqlAsyncError::DeserializeError { index, column, .. } => {
+            assert_eq!(index, 2);
+            assert_eq!(column, "account_name");
+        }
+        err => panic!("expected deserialize error, got {:?}", err),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_deserialize_error_field_path() -> Result<(), ToqlMySqlAsyncError> {
+    // Table alias and column of the select are resolved to the mapped field
+    let columns: Arc<[Column]> = ["id", "active"]
+        .iter()
+        .map(|c| {
+            Column::new(ColumnType::MYSQL_TYPE_VAR_STRING)
+                .with_table(b"member")
+                .with_org_table(b"legacy_members")
+                .with_name(c.as_bytes())
+                .with_org_name(c.as_bytes())
+        })
+        .collect::<Vec<_>>()
+        .into();
+    let mut conn = MockConn::new();
+    conn.push_result(MockResult::Rows(vec![mysql_common::row::new_row(
+        vec![Value::UInt(1), Value::Bytes(b"maybe".to_vec())],
+        columns,
+    )]));
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    let err = toql.load_many(query!(Member, "*")).await.unwrap_err();
+    assert_eq!(err.to_string(), "Member.active: expected bool, got VARCHAR");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_row_get() -> Result<(), ToqlMySqlAsyncError> {
+    let server = TestServer::start().await.unwrap();
+    server.push(Reply::ResultSet(ResultSet::new(
+        &["id", "name"],
+        vec![vec![Value::Int(7), Value::NULL]],
+    )));
+
+    let mut conn = mysql_async::Conn::from_url(server.url()).await?;
+    let row: mysql_async::Row = conn.query_first("SELECT id, name FROM User").await?.unwrap();
+    let row = Row::from(row);
+
+    let id: Option<i64> = crate::mysql_row_get!(row, "id")?;
+    assert_eq!(id, Some(7));
+    let name: Option<String> = crate::mysql_row_get!(row, 1)?;
+    assert_eq!(name, None);
+    assert!(matches!(
+        row.try_get::<i64, _>("email"),
+        Err(ToqlMySqlAsyncError::ColumnNotFoundError(_))
+    ));
+    assert!(matches!(
+        row.try_get::<i64, _>(2),
+        Err(ToqlMySqlAsyncError::ColumnNotFoundError(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_numeric_mode() -> Result<(), ToqlMySqlAsyncError> {
+    let server = TestServer::start().await.unwrap();
+    let result = || {
+        Reply::ResultSet(ResultSet::new(
+            &["customer_id", "amount", "account_name"],
+            vec![vec![Value::Int(1), Value::UInt(5_000_000_000), Value::NULL]],
+        ))
+    };
+    server.push(result()).push(result());
+
+    let conn = mysql_async::Conn::from_url(server.url()).await?;
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    let err = toql
+        .load_many(query!(Payment, "*"))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "amount: value `5000000000` doesn't fit into i32"
+    );
+
+    toql.settings_mut().set_numeric_mode(NumericMode::Lenient);
+    let payments = toql.load_many(query!(Payment, "*")).await?;
+    assert_eq!(payments[0].amount, i32::MAX);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_errors() -> Result<(), ToqlMySqlAsyncError> {
+    let server = TestServer::start().await.unwrap();
+    server
+        .push(Reply::Error {
+            code: 1062,
+            state: "23000".to_string(),
+            message: "Duplicate entry '1' for key 'PRIMARY'".to_string(),
+        })
+        .push(Reply::Disconnect);
+
+    let mut conn = mysql_async::Conn::from_url(server.url()).await?;
+    match conn.query_drop("INSERT INTO Payment VALUES (1, 2, NULL)").await {
+        Err(mysql_async::Error::Server(err)) => assert_eq!(err.code, 1062),
+        other => panic!("expected server error, got {:?}", other),
+    }
+    assert!(conn.query_drop("SELECT 1").await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_duplicate_key() -> Result<(), ToqlMySqlAsyncError> {
+    let mut conn = MockConn::new();
+    conn.push_rows(&["Field", "Type"], vec![])
+        .push_server_error(1062, "23000", "Duplicate entry '1' for key 'Payment.PRIMARY'");
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    let mut payment = Payment {
+        customer_id: 1,
+        amount: 2,
+        account_name: None,
+    };
+    let err = toql.insert_one(&mut payment, paths!(top)).await.unwrap_err();
+
+    assert!(err.is_conflict());
+    assert!(!err.is_retryable());
+    assert_eq!(err.server_code(), Some(1062));
+    assert!(err.to_string().starts_with("insert into Payment failed: "));
+    let context = err.context().unwrap();
+    assert_eq!(context.operation(), Some("insert into"));
+    assert_eq!(context.entity(), Some("Payment"));
+    assert!(context.statement().unwrap().starts_with("INSERT INTO Payment"));
+    assert_eq!(context.args(), None);
+    match err {
+        ToqlMySqlAsyncError::DuplicateKeyError { index, .. } => assert_eq!(index, "Payment.PRIMARY"),
+        err => panic!("expected duplicate key, got {:?}", err),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_unclassified_error_context() -> Result<(), ToqlMySqlAsyncError> {
+    let mut conn = MockConn::new();
+    conn.push_server_error(1146, "42S02", "Table 'test.Payment' doesn't exist")
+        .push_result(MockResult::Error(
+            mysql_async::DriverError::ConnectionClosed.into(),
+        ));
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    toql.settings_mut().set_error_args(ErrorArgs::Plain);
+
+    // Server error without own variant
+    let err = toql
+        .load_many(query!(Payment, "*, customer_id eq ?", 5))
+        .await
+        .unwrap_err();
+    assert_eq!(err.server_code(), Some(1146));
+    assert!(err.to_string().starts_with("load Payment failed: "));
+    let context = err.context().unwrap();
+    assert_eq!(context.operation(), Some("load"));
+    assert_eq!(context.entity(), Some("Payment"));
+    assert!(context.statement().unwrap().starts_with("SELECT"));
+    assert_eq!(context.args(), Some(&["5".to_string()][..]));
+    assert!(matches!(err, ToqlMySqlAsyncError::MySqlError { .. }));
+
+    // Driver error
+    let err = toql.count(query!(Payment, "*")).await.unwrap_err();
+    assert_eq!(err.server_code(), None);
+    let context = err.context().unwrap();
+    assert_eq!(context.operation(), Some("count"));
+    assert!(context.statement().unwrap().starts_with("SELECT COUNT"));
+    match err {
+        ToqlMySqlAsyncError::MySqlError {
+            error: mysql_async::Error::Driver(mysql_async::DriverError::ConnectionClosed),
+            ..
+        } => {}
+        err => panic!("expected driver error, got {:?}", err),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_retry_deadlock() -> Result<(), ToqlMySqlAsyncError> {
+    let server = TestServer::start().await.unwrap();
+    let ok = || Reply::Ok {
+        affected_rows: 0,
+        last_insert_id: 0,
+    };
+    server
+        .push(ok()) // START TRANSACTION
+        .push(Reply::Error {
+            code: 1213,
+            state: "40001".to_string(),
+            message: "Deadlock found when trying to get lock; try restarting transaction"
+                .to_string(),
+        })
+        .push(ok()) // ROLLBACK
+        .push(ok()) // START TRANSACTION
+        .push(ok()) // UPDATE
+        .push(ok()); // COMMIT
+
+    let conn = mysql_async::Conn::from_url(server.url()).await?;
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    toql.settings_mut().set_retry_policy(Some(RetryPolicy::new(3).with_backoff(
+        std::time::Duration::from_millis(1),
+        std::time::Duration::from_millis(1),
+    )));
+
+    let mut attempts = 0;
+    toql.retry_transaction(TxOpts::default(), |tx| {
+        attempts += 1;
+        Box::pin(async move {
+            tx.conn().query_drop("UPDATE Payment SET amount = 3").await?;
+            Ok(())
+        })
+    })
+    .await?;
+
+    assert_eq!(attempts, 2);
+    let statements = server.statements();
+    assert!(statements.last().unwrap().sql.starts_with("COMMIT"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_pool_retry_deadlock() -> Result<(), ToqlMySqlAsyncError> {
+    let server = TestServer::start().await.unwrap();
+    let ok = || Reply::Ok {
+        affected_rows: 0,
+        last_insert_id: 0,
+    };
+    server
+        .push(ok()) // START TRANSACTION
+        .push(Reply::Error {
+            code: 1213,
+            state: "40001".to_string(),
+            message: "Deadlock found when trying to get lock; try restarting transaction"
+                .to_string(),
+        })
+        .push(ok()) // ROLLBACK
+        .push(ok()) // START TRANSACTION
+        .push(ok()) // UPDATE
+        .push(ok()); // COMMIT
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsyncPool::from(Pool::new(server.url().as_str()), &cache);
+    toql.settings_mut().set_retry_policy(Some(RetryPolicy::new(3).with_backoff(
+        std::time::Duration::from_millis(1),
+        std::time::Duration::from_millis(1),
+    )));
+
+    let mut attempts = 0;
+    toql.retry_transaction(TxOpts::default(), |tx| {
+        attempts += 1;
+        Box::pin(async move {
+            tx.conn().query_drop("UPDATE Payment SET amount = 3").await?;
+            Ok(())
+        })
+    })
+    .await?;
+
+    assert_eq!(attempts, 2);
+    let statements = server.statements();
+    assert!(statements.last().unwrap().sql.starts_with("COMMIT"));
+    Ok(())
+}
+
+#[test]
+fn retry_jitter() {
+    let policy = RetryPolicy::new(3).with_backoff(
+        std::time::Duration::from_millis(100),
+        std::time::Duration::from_secs(1),
+    );
+    let delays = (0..8).map(|_| policy.backoff(1)).collect::<HashSet<_>>();
+    assert!(delays.len() > 1);
+    assert!(delays.iter().all(|d| d.as_millis() >= 50 && d.as_millis() <= 100));
+}
+
+#[tokio::test]
+async fn test_server_pool() -> Result<(), ToqlMySqlAsyncError> {
+    let server = TestServer::start().await.unwrap();
+    server
+        .push(Reply::Error {
+            code: 1146,
+            state: "42S02".to_string(),
+            message: "Table 'test.Payment' doesn't exist".to_string(),
+        })
+        .push(Reply::ResultSet(ResultSet::new(
+            &["customer_id", "amount", "account_name"],
+            vec![vec![Value::Int(1), Value::Int(2), Value::NULL]],
+        )));
+
+    // Pool with a single connection
+    let opts = OptsBuilder::from_opts(Opts::from_url(&server.url()).unwrap()).pool_opts(
+        PoolOpts::default().with_constraints(PoolConstraints::new(1, 1).unwrap()),
+    );
+    let cache = Cache::default();
+    let mut toql = MySqlAsyncPool::from(Pool::new(opts), &cache);
+    toql.set_acquire_timeout(Some(std::time::Duration::from_millis(200)));
+
+    // Failing call gives its connection back
+    let err = toql.load_many(query!(Payment, "*")).await.unwrap_err();
+    assert_eq!(err.server_code(), Some(1146));
+    let payments = toql.load_many(query!(Payment, "*")).await?;
+    assert_eq!(payments.len(), 1);
+
+    let conn = toql.get_conn().await?;
+    match toql.load_many(query!(Payment, "*")).await {
+        Err(ToqlMySqlAsyncError::PoolTimeout(_)) => {}
+        other => panic!("expected pool timeout, got {:?}", other),
+    }
+    drop(conn);
+
+    let statements = server.statements();
+    assert_eq!(statements.len(), 2);
+    assert!(statements.iter().all(|s| s.sql.starts_with("SELECT")));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_transaction_context() -> Result<(), ToqlMySqlAsyncError> {
+    let server = TestServer::start().await.unwrap();
+    server.on(
+        "UPDATE",
+        Reply::Error {
+            code: 1146,
+            state: "42S02".to_string(),
+            message: "Table 'test.Payment' doesn't exist".to_string(),
+        },
+    );
+
+    let conn = mysql_async::Conn::from_url(server.url()).await?;
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    let roles: HashSet<String> = vec!["admin".to_string()].into_iter().collect();
+    toql.set_roles(roles.clone());
+
+    // Cancelled transaction
+    let cancelled = tokio::time::timeout(
+        std::time::Duration::from_millis(50),
+        toql.transaction(TxOpts::default(), |_| {
+            Box::pin(futures_util::future::pending::<Result<(), ToqlMySqlAsyncError>>())
+        }),
+    )
+    .await;
+    assert!(cancelled.is_err());
+    assert_eq!(toql.roles(), &roles);
+
+    // Failed transaction
+    let err = toql
+        .transaction(TxOpts::default(), |tx| {
+            Box::pin(async move {
+                tx.conn().query_drop("UPDATE Payment SET amount = 3").await?;
+                Ok(())
+            })
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.server_code(), Some(1146));
+    assert_eq!(toql.roles(), &roles);
+    assert!(server.take_statements().last().unwrap().sql.starts_with("ROLLBACK"));
+
+    // Committed transaction keeps changes of the context
+    toql.transaction(TxOpts::default(), |tx| {
+        Box::pin(async move {
+            tx.set_aux_param("tenant".to_string(), SqlArg::U64(1));
+            Ok(())
+        })
+    })
+    .await?;
+    assert_eq!(toql.roles(), &roles);
+    assert_eq!(toql.aux_params().get("tenant"), Some(&SqlArg::U64(1)));
+    assert!(server.statements().last().unwrap().sql.starts_with("COMMIT"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_nested_rollback() -> Result<(), ToqlMySqlAsyncError> {
+    let server = TestServer::start().await.unwrap();
+    server
+        .on(
+            "UPDATE",
+            Reply::Error {
+                code: 1062,
+                state: "23000".to_string(),
+                message: "Duplicate entry '1' for key 'Payment.PRIMARY'".to_string(),
+            },
+        )
+        .on(
+            "ROLLBACK TO SAVEPOINT `broken`",
+            Reply::Error {
+                code: 1305,
+                state: "42000".to_string(),
+                message: "SAVEPOINT broken does not exist".to_string(),
+            },
+        );
+
+    let conn = mysql_async::Conn::from_url(server.url()).await?;
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    toql.transaction(TxOpts::default(), |tx| {
+        Box::pin(async move {
+            let err = tx
+                .nested("payments", |tx| {
+                    Box::pin(async move {
+                        tx.conn().query_drop("UPDATE Payment SET amount = 3").await?;
+                        Ok(())
+                    })
+                })
+                .await
+                .unwrap_err();
+            assert!(matches!(err, ToqlMySqlAsyncError::DuplicateKeyError { .. }));
+
+            let err = tx
+                .nested("broken", |tx| {
+                    Box::pin(async move {
+                        tx.conn().query_drop("UPDATE Payment SET amount = 3").await?;
+                        Ok(())
+                    })
+                })
+                .await
+                .unwrap_err();
+            match err {
+                ToqlMySqlAsyncError::RollbackError { error, rollback } => {
+                    assert_eq!(error.server_code(), Some(1062));
+                    assert_eq!(rollback.server_code(), Some(1305));
+                    assert_eq!(
+                        rollback.context().unwrap().statement(),
+                        Some("ROLLBACK TO SAVEPOINT `broken`")
+                    );
+                }
+                err => panic!("expected rollback error, got {:?}", err),
+            }
+            Ok(())
+        })
+    })
+    .await?;
+
+    let statements = server
+        .statements()
+        .into_iter()
+        .map(|s| s.sql)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        &statements[1..5],
+        &[
+            "SAVEPOINT `payments`",
+            "UPDATE Payment SET amount = 3",
+            "ROLLBACK TO SAVEPOINT `payments`",
+            "RELEASE SAVEPOINT `payments`",
+        ]
+    );
+    assert!(!statements.contains(&"RELEASE SAVEPOINT `broken`".to_string()));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_stream() -> Result<(), ToqlMySqlAsyncError> {
+    let server = TestServer::start().await.unwrap();
+    server.push(Reply::ResultSet(ResultSet::new(
+        &["customer_id", "amount", "account_name"],
+        vec![
+            vec![Value::Int(1), Value::Int(2), Value::NULL],
+            vec![Value::Int(3), Value::Int(4), Value::Bytes(b"foo".to_vec())],
+            vec![Value::Int(5), Value::NULL, Value::NULL],
+        ],
+    )));
+
+    let conn = mysql_async::Conn::from_url(server.url()).await?;
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    let mut payments = toql.load_stream(query!(Payment, "*")).await?;
+
+    assert_eq!(
+        payments.try_next().await?,
+        Some(Payment {
+            customer_id: 1,
+            amount: 2,
+            account_name: None,
+        })
+    );
+    assert_eq!(
+        payments.try_next().await?,
+        Some(Payment {
+            customer_id: 3,
+            amount: 4,
+            account_name: Some("foo".to_string()),
+        })
+    );
+    // Row with NULL in a required field fails, but not the rows before
+    assert!(payments.try_next().await.is_err());
+    drop(payments);
+
+    let statements = server.statements();
+    assert_eq!(statements.len(), 1);
+    assert!(statements[0].prepared);
+    assert!(statements[0].sql.starts_with("SELECT"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_keyset_pages() -> Result<(), ToqlMySqlAsyncError> {
+    let columns = [
+        "toql_cursor_0",
+        "toql_cursor_1",
+        "customer_id",
+        "amount",
+        "account_name",
+    ];
+    let row = |customer_id: i64, amount: i64| {
+        vec![
+            Value::Int(amount),
+            Value::Int(customer_id),
+            Value::Int(customer_id),
+            Value::Int(amount),
+            Value::NULL,
+        ]
+    };
+    let mut conn = MockConn::new();
+    conn.push_rows(&columns, vec![row(1, 9), row(5, 9)])
+        .push_rows(&columns, vec![row(3, 4)]);
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    let query = query!(Payment, "-amount, *");
+
+    let (payments, info) = toql
+        .load_paged(&query, crate::paging::Page::Keyset(None, 2))
+        .await?;
+    let ids = payments.iter().map(|p| p.customer_id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![1, 5]);
+    let cursor = info.next.unwrap();
+    assert_eq!(cursor.to_string(), "i9,i5");
+
+    let (payments, info) = toql
+        .load_paged(&query, crate::paging::Page::Keyset(Some(cursor), 2))
+        .await?;
+    let ids = payments.iter().map(|p| p.customer_id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![3]);
+    assert!(info.next.is_none());
+
+    let statements = toql.conn().take_statements();
+    assert_eq!(statements.len(), 2);
+    assert!(statements[0].sql.contains(" AS toql_cursor_1,"));
+    assert!(statements[0].params.is_empty());
+    assert!(statements[1].sql.contains(" < ?) OR ("));
+    assert_eq!(
+        statements[1].params,
+        vec![Value::Int(9), Value::Int(9), Value::Int(5)]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_insert_rows_atomically() -> Result<(), ToqlMySqlAsyncError> {
+    let payment = |customer_id| Payment {
+        customer_id,
+        amount: 2,
+        account_name: None,
+    };
+    let mut conn = MockConn::new();
+    conn.push_rows(&["Field", "Type"], id_column("int"))
+        .push_rows(&["@@innodb_autoinc_lock_mode"], vec![vec![Value::Int(2)]])
+    .push_ok(0, None) // START TRANSACTION
+    .push_ok(1, Some(10))
+    .push_ok(1, Some(11))
+    .push_ok(0, None) // COMMIT
+    .push_ok(0, None) // START TRANSACTION
+    .push_ok(1, Some(12))
+    .push_server_error(1062, "23000", "Duplicate entry '3' for key 'Payment.PRIMARY'");
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    toql.insert_many(&mut [payment(1), payment(2)], paths!(top))
+        .await?;
+    let statements = toql.conn().take_statements();
+    assert_eq!(statements.len(), 6);
+    assert!(statements[0].sql.starts_with("SHOW COLUMNS FROM `Payment`"));
+    assert_eq!(statements[2].sql, "START TRANSACTION");
+    assert!(statements[3].sql.starts_with("INSERT INTO Payment"));
+    assert!(statements[4].sql.starts_with("INSERT INTO Payment"));
+    assert_eq!(statements[5].sql, "COMMIT");
+
+    // Failing row rolls back the rows before
+    let err = toql
+        .insert_many(&mut [payment(3), payment(4)], paths!(top))
+        .await
+        .unwrap_err();
+    assert_eq!(err.server_code(), Some(1062));
+    let statements = toql.conn().take_statements();
+    assert_eq!(statements.len(), 4);
+    assert_eq!(statements[0].sql, "START TRANSACTION");
+    assert_eq!(statements[3].sql, "ROLLBACK");
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_insert_rows_in_savepoint() -> Result<(), ToqlMySqlAsyncError> {
+    let payment = |customer_id| Payment {
+        customer_id,
+        amount: 2,
+        account_name: None,
+    };
+    let mut conn = MockConn::new();
+    conn.set_in_transaction(true)
+        .push_rows(&["Field", "Type"], id_column("int"))
+        .push_rows(&["@@innodb_autoinc_lock_mode"], vec![vec![Value::Int(2)]]);
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    let mut savepoints = Vec::new();
+    for _ in 0..2 {
+        toql.insert_many(&mut [payment(1), payment(2)], paths!(top))
+            .await?;
+        let statements = toql.conn().take_statements();
+        let savepoint = statements
+            .iter()
+            .find(|s| s.sql.starts_with("SAVEPOINT "))
+            .map(|s| s.sql["SAVEPOINT ".len()..].to_string())
+            .unwrap();
+        assert!(savepoint.starts_with("toql_insert_"));
+        assert_eq!(
+            statements.last().unwrap().sql,
+            format!("RELEASE SAVEPOINT {}", savepoint)
+        );
+        savepoints.push(savepoint);
+    }
+    assert_ne!(savepoints[0], savepoints[1]);
+    Ok(())
+}
+
+/// Result of `SHOW COLUMNS` with an auto increment column `id` of the given type.
+fn id_column(column_type: &str) -> Vec<Vec<Value>> {
+    vec![vec![
+        Value::Bytes(b"id".to_vec()),
+        Value::Bytes(column_type.as_bytes().to_vec()),
+    ]]
+}
+
+#[tokio::test]
+async fn mock_insert_id_sign_from_column_type() -> Result<(), ToqlMySqlAsyncError> {
+    for (column_type, signed) in vec![
+        ("int", true),
+        ("bigint(20)", true),
+        ("int unsigned", false),
+        ("bigint(20) UNSIGNED", false),
+    ] {
+        let mut conn = MockConn::new();
+        conn.push_rows(&["Field", "Type"], id_column(column_type))
+            .push_ok(1, Some(10));
+
+        let cache = Cache::default();
+        let mut backend = MySqlAsyncBackend::new(conn, &cache, Context::default(), Settings::default());
+        let sql = Sql(
+            "INSERT INTO Payment (customer_id, amount) VALUES (?, ?)".to_string(),
+            vec![SqlArg::I64(1), SqlArg::I64(2)],
+        );
+        let ids = backend.insert_sql(sql).await?;
+        assert_eq!(backend.conn.take_statements().len(), 2);
+        match ids.as_slice() {
+            [SqlArg::I64(10)] => assert!(signed),
+            [SqlArg::U64(10)] => assert!(!signed),
+            ids => panic!("unexpected ids {:?}", ids),
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn mock_insert_explicit_auto_increment_keys() -> Result<(), ToqlMySqlAsyncError> {
+    let mut conn = MockConn::new();
+    conn.push_rows(&["Field", "Type"], id_column("int unsigned"))
+        .push_ok(2, Some(0));
+
+    let cache = Cache::default();
+    let mut backend = MySqlAsyncBackend::new(conn, &cache, Context::default(), Settings::default());
+    let sql = Sql(
+        "INSERT INTO Payment (`id`, amount) VALUES (?, ?), (?, ?)".to_string(),
+        vec![SqlArg::U64(1), SqlArg::I64(2), SqlArg::U64(3), SqlArg::I64(4)],
+    );
+    // No readback and no split into single row inserts
+    assert!(backend.insert_sql(sql).await?.is_empty());
+    let statements = backend.conn.take_statements();
+    assert_eq!(statements.len(), 2);
+    assert!(statements[1].sql.starts_with("INSERT INTO Payment (`id`, amount) VALUES (?, ?), (?, ?)"));
+    assert_eq!(backend.conn.pending_results(), 0);
+    Ok(())
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn time_roundtrip() -> Result<(), ToqlMySqlAsyncError> {
+    use crate::sql_arg::time::{date_arg, primitive_datetime_arg, time_arg};
+
+    let columns: Arc<[Column]> = vec![
+        Column::new(ColumnType::MYSQL_TYPE_DATE).with_name(b"day"),
+        Column::new(ColumnType::MYSQL_TYPE_TIME).with_name(b"time"),
+        Column::new(ColumnType::MYSQL_TYPE_DATETIME).with_name(b"at"),
+    ]
+    .into();
+    let row = Row::new(mysql_common::row::new_row(
+        vec![
+            Value::Date(2022, 3, 21, 0, 0, 0, 0),
+            Value::Time(false, 0, 23, 30, 5, 500_000),
+            Value::Date(2022, 3, 21, 23, 30, 5, 500_000),
+        ],
+        columns,
+    ));
+    let day: time::Date = row.try_get("day")?.unwrap();
+    let time: time::Time = row.try_get("time")?.unwrap();
+    let at: time::PrimitiveDateTime = row.try_get("at")?.unwrap();
+    assert_eq!(
+        day,
+        time::Date::from_calendar_date(2022, time::Month::March, 21).unwrap()
+    );
+    assert_eq!(time, time::Time::from_hms_micro(23, 30, 5, 500_000).unwrap());
+    assert_eq!(at, day.with_time(time));
+
+    // Arguments are read back as the same value
+    let day_value = value_from(date_arg(&day));
+    assert_eq!(day_value, Value::Bytes(b"2022-03-21".to_vec()));
+    assert_eq!(mysql_async::from_value::<time::Date>(day_value), day);
+    assert_eq!(mysql_async::from_value::<time::Time>(value_from(time_arg(&time))), time);
+    assert_eq!(
+        mysql_async::from_value::<time::PrimitiveDateTime>(value_from(primitive_datetime_arg(&at))),
+        at
+    );
+    Ok(())
+}
+
+#[cfg(feature = "rust_decimal")]
+#[derive(Debug, PartialEq, Eq, Clone, Toql)]
+struct Price {
+    #[toql(key)]
+    id: u64,
+    amount: Numeric<rust_decimal::Decimal>,
+}
+
+#[cfg(feature = "rust_decimal")]
+#[tokio::test]
+async fn mock_rust_decimal_roundtrip() -> Result<(), ToqlMySqlAsyncError> {
+    let text = "7922816251426433759354.3950335";
+    let mut price = Price {
+        id: 1,
+        amount: Numeric(text.parse().unwrap()),
+    };
+    let mut conn = MockConn::new();
+    conn.push_rows(&["Field", "Type"], vec![]) // No auto increment column
+        .push_ok(1, None)
+        .push_rows(
+            &["id", "amount"],
+            vec![vec![Value::UInt(1), Value::Bytes(text.as_bytes().to_vec())]],
+        );
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    toql.insert_one(&mut price, paths!(top)).await?;
+    assert_eq!(toql.load_one(query!(Price, "id eq 1, *")).await?, price);
+
+    let statements = toql.conn().take_statements();
+    assert_eq!(
+        statements[1].params,
+        vec![Value::UInt(1), Value::Bytes(text.as_bytes().to_vec())]
+    );
+    Ok(())
+}
+
+#[cfg(feature = "bigdecimal")]
+#[derive(Debug, PartialEq, Eq, Clone, Toql)]
+struct BigPrice {
+    #[toql(key)]
+    id: u64,
+    amount: Numeric<bigdecimal::BigDecimal>,
+}
+
+#[cfg(feature = "bigdecimal")]
+#[tokio::test]
+async fn mock_bigdecimal_roundtrip() -> Result<(), ToqlMySqlAsyncError> {
+    let text = "123456789012345678901234567890.123456789012345678901234567890";
+    let mut price = BigPrice {
+        id: 1,
+        amount: Numeric(text.parse().unwrap()),
+    };
+    let mut conn = MockConn::new();
+    conn.push_rows(&["Field", "Type"], vec![]) // No auto increment column
+        .push_ok(1, None)
+        .push_rows(
+            &["id", "amount"],
+            vec![vec![Value::UInt(1), Value::Bytes(text.as_bytes().to_vec())]],
+        );
+
+    let cache = Cache::default();
+    let mut toql = MySqlAsync::from(conn, &cache);
+    toql.insert_one(&mut price, paths!(top)).await?;
+    assert_eq!(toql.load_one(query!(BigPrice, "id eq 1, *")).await?, price);
+
+    let statements = toql.conn().take_statements();
+    assert_eq!(
+        statements[1].params,
+        vec![Value::UInt(1), Value::Bytes(text.as_bytes().to_vec())]
+    );
+    Ok(())
+}
+
+/// Deserialize a single column value into a field of type `T`.
+fn field<T>(column_type: ColumnType, value: Value) -> Result<Option<T>, ToqlMySqlAsyncError>
+where
+    T: FromRow<Row, ToqlMySqlAsyncError>,
+{
+    field_with(RowSettings::default(), column_type, value)
+}
+
+/// Deserialize a single column value into a field of type `T` with the given settings.
+fn field_with<T>(
+    settings: RowSettings,
+    column_type: ColumnType,
+    value: Value,
+) -> Result<Option<T>, ToqlMySqlAsyncError>
+where
+    T: FromRow<Row, ToqlMySqlAsyncError>,
+{
+    let row = column_row(settings, column_type, value);
+    let selects = [Select::Query];
+    T::from_row(&row, &mut 0, &mut selects.iter())
+}
+
+fn is_numeric_error<T>(result: Result<T, ToqlMySqlAsyncError>) -> bool {
+    matches!(result, Err(ToqlMySqlAsyncError::NumericError { .. }))
+}
+
+/// Row with a single column.
+fn column_row(settings: RowSettings, column_type: ColumnType, value: Value) -> Row {
+    let columns: Arc<[Column]> = vec![Column::new(column_type).with_name(b"field")].into();
+    Row(
+        mysql_common::row::new_row(vec![value], columns),
+        Arc::new(settings),
+        None,
+    )
+}
+
+#[test]
+fn numeric_precision() -> Result<(), ToqlMySqlAsyncError> {
+    let strict = || RowSettings::default();
+    let lenient = || RowSettings {
+        numeric_mode: NumericMode::Lenient,
+        ..RowSettings::default()
+    };
+    let decimal = |s: &str| Value::Bytes(s.as_bytes().to_vec());
+
+    // Floats
+    let double = ColumnType::MYSQL_TYPE_DOUBLE;
+    assert!(is_numeric_error(field_with::<f32>(strict(), double, Value::Double(0.1))));
+    assert_eq!(field_with::<f32>(lenient(), double, Value::Double(0.1))?, Some(0.1));
+    assert_eq!(field_with::<f32>(strict(), double, Value::Double(0.5))?, Some(0.5));
+    assert_eq!(
+        field_with::<f64>(strict(), ColumnType::MYSQL_TYPE_FLOAT, Value::Float(0.1))?,
+        Some(f64::from(0.1f32))
+    );
+
+    // Decimals are exact, if the float prints as the decimal
+    let dec = ColumnType::MYSQL_TYPE_NEWDECIMAL;
+    assert_eq!(field_with::<f64>(strict(), dec, decimal("12.50"))?, Some(12.5));
+    assert_eq!(field_with::<f64>(strict(), dec, decimal("-0.10"))?, Some(-0.1));
+    match field_with::<f64>(strict(), dec, decimal("12345678901234567.89")) {
+        Err(ToqlMySqlAsyncError::NumericError {
+            value, expected, ..
+        }) => {
+            assert_eq!(value, "12345678901234567.89");
+            assert_eq!(expected, "f64");
+        }
+        r => panic!("Expected numeric error, got {:?}", r),
+    }
+    assert_eq!(
+        field_with::<f64>(lenient(), dec, decimal("12345678901234567.89"))?,
+        Some(12345678901234568.0)
+    );
+    assert!(is_numeric_error(field_with::<f32>(strict(), dec, decimal("0.123456789"))));
+    assert_eq!(
+        field_with::<f32>(lenient(), dec, decimal("0.123456789"))?,
+        Some(0.123456789)
+    );
+    assert!(is_numeric_error(field_with::<f32>(strict(), dec, decimal("16777217"))));
+
+    // Decimals into integers
+    assert_eq!(field_with::<i64>(strict(), dec, decimal("42.00"))?, Some(42));
+    assert!(is_numeric_error(field_with::<i64>(strict(), dec, decimal("42.50"))));
+    assert_eq!(field_with::<i64>(lenient(), dec, decimal("42.50"))?, Some(43));
+    Ok(())
+}
+
+#[test]
+fn invalid_dates() {
+    assert!(is_invalid_date(&Value::Date(0, 0, 0, 0, 0, 0, 0)));
+    assert!(is_invalid_date(&Value::Date(2022, 2, 30, 0, 0, 0, 0)));
+    assert!(is_invalid_date(&Value::Bytes(b"0000-00-00".to_vec())));
+    assert!(is_invalid_date(&Value::Bytes(b"2022-04-31 10:00:00".to_vec())));
+    assert!(!is_invalid_date(&Value::Date(2020, 2, 29, 0, 0, 0, 0)));
+    assert!(!is_invalid_date(&Value::Bytes(b"2022-02-28 10:00:00.5".to_vec())));
+    assert!(!is_invalid_date(&Value::Bytes(b"foo".to_vec())));
+    assert!(!is_invalid_date(&Value::Int(0)));
+}
+
+#[test]
+fn zero_date_policies() -> Result<(), ToqlMySqlAsyncError> {
+    let zero = || Value::Bytes(b"0000-00-00".to_vec());
+    let settings = |policy| RowSettings {
+        zero_date_policy: policy,
+        ..RowSettings::default()
+    };
+
+    match field::<NaiveDate>(ColumnType::MYSQL_TYPE_DATE, zero()) {
+        Err(ToqlMySqlAsyncError::ZeroDateError { field, value, .. }) => {
+            assert_eq!(field, "field");
+            assert_eq!(value, "0000-00-00");
+        }
+        r => panic!("Expected zero date error, got {:?}", r),
+    }
+    assert_eq!(
+        field_with::<NaiveDate>(settings(ZeroDatePolicy::Null), ColumnType::MYSQL_TYPE_DATE, zero())?,
+        None
+    );
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    assert_eq!(
+        field_with::<NaiveDateTime>(
+            settings(ZeroDatePolicy::Sentinel(epoch)),
+            ColumnType::MYSQL_TYPE_DATE,
+            zero()
+        )?,
+        Some(epoch)
+    );
+
+    // Other types get the stored value with every policy
+    assert_eq!(
+        field::<String>(ColumnType::MYSQL_TYPE_DATE, zero())?,
+        Some("0000-00-00".to_string())
+    );
+    let row = column_row(settings(ZeroDatePolicy::Null), ColumnType::MYSQL_TYPE_DATE, zero());
+    assert_eq!(row.try_get::<String, _>(0)?, Some("0000-00-00".to_string()));
+    assert_eq!(row.try_get::<NaiveDate, _>(0)?, None);
+    let row = column_row(RowSettings::default(), ColumnType::MYSQL_TYPE_DATE, zero());
+    assert!(matches!(
+        row.try_get::<NaiveDate, _>(0),
+        Err(ToqlMySqlAsyncError::ZeroDateError { .. })
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn uuid_layouts() -> Result<(), ToqlMySqlAsyncError> {
+    use crate::row::SwappedUuid;
+    use crate::sql_arg::swapped_uuid_arg;
+
+    let uuid = uuid::Uuid::parse_str("6ccd780c-baba-1026-9564-5b8c656024db").unwrap();
+    let text = Value::Bytes(uuid.to_string().into_bytes());
+    let binary = Value::Bytes(uuid.as_bytes().to_vec());
+    let swapped = value_from(swapped_uuid_arg(&uuid));
+    assert_eq!(
+        swapped,
+        Value::Bytes(vec![
+            0x10, 0x26, 0xba, 0xba, 0x6c, 0xcd, 0x78, 0x0c, 0x95, 0x64, 0x5b, 0x8c, 0x65, 0x60,
+            0x24, 0xdb
+        ])
+    );
+
+    // Text
+    let string = ColumnType::MYSQL_TYPE_STRING;
+    assert_eq!(field::<uuid::Uuid>(string, text.clone())?, Some(uuid));
+    assert_eq!(field::<SwappedUuid>(string, text)?, Some(SwappedUuid(uuid)));
+
+    // Binary in RFC 4122 order
+    assert_eq!(field::<uuid::Uuid>(string, binary)?, Some(uuid));
+
+    // Swapped binary
+    assert_eq!(field::<SwappedUuid>(string, swapped.clone())?, Some(SwappedUuid(uuid)));
+    assert_ne!(field::<uuid::Uuid>(string, swapped)?, Some(uuid));
+
+    assert!(matches!(
+        field::<uuid::Uuid>(string, Value::Bytes(b"not a uuid".to_vec())),
+        Err(ToqlMySqlAsyncError::DeserializeError { .. })
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_roundtrip() -> Result<(), ToqlMySqlAsyncError> {
+    use crate::row::Json;
+    use std::collections::BTreeMap;
+    use std::convert::TryFrom;
+
+    let mut map = BTreeMap::new();
+    map.insert("a".to_string(), vec![1u32, 2]);
+    map.insert("b".to_string(), vec![]);
+    let value = value_from(SqlArg::try_from(&Json(map.clone()))?);
+    assert_eq!(value, Value::Bytes(br#"{"a":[1,2],"b":[]}"#.to_vec()));
+    assert_eq!(
+        field::<Json<BTreeMap<String, Vec<u32>>>>(ColumnType::MYSQL_TYPE_JSON, value)?,
+        Some(Json(map))
+    );
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_arg_failure() {
+    use crate::row::Json;
+    use crate::sql_arg::json_arg;
+    use std::collections::BTreeMap;
+    use std::convert::TryFrom;
+
+    // JSON has no sequences as map keys
+    let mut map = BTreeMap::new();
+    map.insert(vec![1u8], 1u8);
+    assert!(matches!(
+        json_arg(&map),
+        Err(ToqlMySqlAsyncError::JsonError(_))
+    ));
+    assert!(matches!(
+        SqlArg::try_from(Json(map)),
+        Err(ToqlMySqlAsyncError::JsonError(_))
+    ));
+}
+
+crate::sql_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Color {
+        Red = "red",
+        Green = "green",
+    }
+}
+
+#[test]
+fn bit_columns() -> Result<(), ToqlMySqlAsyncError> {
+    let bit = ColumnType::MYSQL_TYPE_BIT;
+    assert_eq!(field::<u64>(bit, Value::Bytes(vec![0x01, 0x02]))?, Some(0x0102));
+    assert_eq!(field::<u8>(bit, Value::Bytes(vec![0x05]))?, Some(5));
+    assert_eq!(field::<bool>(bit, Value::Bytes(vec![0x01]))?, Some(true));
+    assert_eq!(field::<bool>(bit, Value::Bytes(vec![0x00]))?, Some(false));
+    // Other types get the raw bytes
+    assert_eq!(field::<Vec<u8>>(bit, Value::Bytes(vec![0x01, 0x02]))?, Some(vec![0x01, 0x02]));
+    // Bytes of other columns are no bits
+    assert_eq!(
+        field::<Vec<u8>>(ColumnType::MYSQL_TYPE_BLOB, Value::Bytes(vec![0x01]))?,
+        Some(vec![0x01])
+    );
+
+    let columns: Arc<[Column]> = vec![Column::new(bit).with_name(b"flags")].into();
+    let row = Row::new(mysql_common::row::new_row(vec![Value::Bytes(vec![0x01, 0x00])], columns));
+    assert_eq!(row.try_get::<u64, _>("flags")?, Some(256));
+    assert_eq!(row.try_get::<Vec<u8>, _>("flags")?, Some(vec![0x01, 0x00]));
+    Ok(())
+}
+
+#[test]
+fn enum_and_set_columns() -> Result<(), ToqlMySqlAsyncError> {
+    use crate::row::Enum;
+
+    let string = ColumnType::MYSQL_TYPE_STRING;
+    assert_eq!(
+        field::<Enum<Color>>(string, Value::Bytes(b"green".to_vec()))?,
+        Some(Enum(Color::Green))
+    );
+    assert_eq!(SqlArg::from(Enum(Color::Red)), SqlArg::Str("red".into()));
+    assert_eq!(
+        field::<HashSet<Color>>(string, Value::Bytes(b"red,GREEN".to_vec()))?,
+        Some(vec![Color::Red, Color::Green].into_iter().collect())
+    );
+    assert_eq!(field::<HashSet<Color>>(string, Value::Bytes(vec![]))?, Some(HashSet::new()));
+
+    // Unknown members name column and value
+    for result in vec![
+        field::<Enum<Color>>(string, Value::Bytes(b"blue".to_vec())).map(|_| ()),
+        field::<HashSet<Color>>(string, Value::Bytes(b"red,blue".to_vec())).map(|_| ()),
+    ] {
+        match result {
+            Err(ToqlMySqlAsyncError::EnumValueError { column, value }) => {
+                assert_eq!(column, "field");
+                assert_eq!(value, "blue");
+            }
+            other => panic!("expected enum value error, got {:?}", other),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "bitflags")]
+bitflags::bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Permissions: u8 {
+        const READ = 1;
+        const WRITE = 2;
+        const DELETE = 4;
+    }
+}
+
+#[cfg(feature = "bitflags")]
+#[test]
+fn set_flags_columns() -> Result<(), ToqlMySqlAsyncError> {
+    use crate::row::SetFlags;
+
+    let string = ColumnType::MYSQL_TYPE_STRING;
+    assert_eq!(
+        field::<SetFlags<Permissions>>(string, Value::Bytes(b"read,delete".to_vec()))?,
+        Some(SetFlags(Permissions::READ | Permissions::DELETE))
+    );
+    assert_eq!(
+        SqlArg::from(SetFlags(Permissions::READ | Permissions::WRITE)),
+        SqlArg::Str("READ,WRITE".into())
+    );
+    assert!(matches!(
+        field::<SetFlags<Permissions>>(string, Value::Bytes(b"read,admin".to_vec())),
+        Err(ToqlMySqlAsyncError::EnumValueError { value, .. }) if value == "admin"
+    ));
+    Ok(())
+}
@@ -0,0 +1,806 @@
+//! A scripted in-process server, that speaks the MySQL wire protocol.
+//!
+//! Unlike [MockConn](crate::mock::MockConn) the [TestServer] is used through a real
+//! [Conn](mysql_async::Conn) or [Pool](mysql_async::Pool). This exercises the driver
+//! with prepared statements, text and binary result sets and error packets.
+//!
+//! Queries of the driver to read its connection settings are answered internally.
+//! Every other statement is recorded and answered by the first matching rule,
+//! then by the next queued reply and otherwise with an empty OK packet.
+//!
+//! ```rust
+//! use toql_mysql_async::test_server::{Reply, ResultSet, TestServer};
+//!
+//! let server = TestServer::start().await?;
+//! server.push(Reply::ResultSet(ResultSet::new(&["id"], vec![vec![Value::Int(1)]])));
+//!
+//! let pool = Pool::new(server.url().as_str());
+//! let mut conn = pool.get_conn().await?;
+//! let id: Option<i64> = conn.exec_first("SELECT id FROM User WHERE id = ?", (1,)).await?;
+//! assert_eq!(server.statements()[0].params, vec![Value::Int(1)]);
+//! ```
+//!
+//! Enable it with the feature `test-server`.
+
+use crate::insert::count_placeholders;
+use mysql_async::{
+    consts::{ColumnFlags, ColumnType},
+    Column, Value,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::TryInto,
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+/// A statement, that was received by the [TestServer].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedStatement {
+    pub sql: String,
+    pub params: Vec<Value>,
+    /// True for prepared statements, false for text queries
+    pub prepared: bool,
+}
+
+/// A scripted answer of the [TestServer].
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// Result set, sent in text or binary protocol depending on the statement
+    ResultSet(ResultSet),
+    /// OK packet
+    Ok {
+        affected_rows: u64,
+        last_insert_id: u64,
+    },
+    /// Error packet
+    Error {
+        code: u16,
+        state: String,
+        message: String,
+    },
+    /// Close the connection without answer
+    Disconnect,
+}
+
+/// Columns and rows of a scripted result set.
+#[derive(Debug, Clone)]
+pub struct ResultSet {
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl ResultSet {
+    /// Result set with the given column names, column types are taken from the values.
+    pub fn new(columns: &[&str], rows: Vec<Vec<Value>>) -> Self {
+        let columns = columns
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let value = rows
+                    .iter()
+                    .filter_map(|r| r.get(i))
+                    .find(|v| **v != Value::NULL);
+                let (column_type, flags) = column_type_of(value);
+                Column::new(column_type)
+                    .with_name(name.as_bytes())
+                    .with_org_name(name.as_bytes())
+                    .with_flags(flags)
+                    .with_character_set(UTF8MB4_GENERAL_CI)
+            })
+            .collect();
+        ResultSet { columns, rows }
+    }
+
+    /// Result set with explicit column definitions.
+    pub fn with_columns(columns: Vec<Column>, rows: Vec<Vec<Value>>) -> Self {
+        ResultSet { columns, rows }
+    }
+}
+
+#[derive(Default)]
+struct Script {
+    statements: Vec<ReceivedStatement>,
+    rules: Vec<(String, Reply)>,
+    replies: VecDeque<Reply>,
+}
+
+impl Script {
+    fn reply(&mut self, statement: ReceivedStatement) -> Reply {
+        if let Some(reply) = settings_reply(&statement.sql) {
+            return reply;
+        }
+        let reply = if let Some((_, reply)) = self
+            .rules
+            .iter()
+            .find(|(prefix, _)| statement.sql.starts_with(prefix.as_str()))
+        {
+            reply.clone()
+        } else {
+            self.replies.pop_front().unwrap_or(Reply::Ok {
+                affected_rows: 0,
+                last_insert_id: 0,
+            })
+        };
+        self.statements.push(statement);
+        reply
+    }
+}
+
+/// A local server, that answers with scripted replies.
+///
+/// The server stops, when it's dropped.
+pub struct TestServer {
+    addr: SocketAddr,
+    script: Arc<Mutex<Script>>,
+    handle: JoinHandle<()>,
+}
+
+impl TestServer {
+    /// Start server on a free local port.
+    pub async fn start() -> io::Result<TestServer> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let script = Arc::new(Mutex::new(Script::default()));
+
+        let accept_script = script.clone();
+        let handle = tokio::spawn(async move {
+            let mut connection_id = 0;
+            while let Ok((socket, _)) = listener.accept().await {
+                connection_id += 1;
+                let script = accept_script.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = serve(socket, script, connection_id).await {
+                        tracing::debug!("Test server connection closed: {}", err);
+                    }
+                });
+            }
+        });
+
+        Ok(TestServer {
+            addr,
+            script,
+            handle,
+        })
+    }
+
+    /// Connection URL for [Pool::new](mysql_async::Pool::new) or [Opts](mysql_async::Opts).
+    pub fn url(&self) -> String {
+        format!(
+            "mysql://test:test@{}:{}/test",
+            self.addr.ip(),
+            self.addr.port()
+        )
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Queue a reply for the next statement, that matches no rule.
+    pub fn push(&self, reply: Reply) -> &Self {
+        self.script.lock().unwrap().replies.push_back(reply);
+        self
+    }
+
+    /// Answer every statement starting with `prefix`.
+    pub fn on(&self, prefix: &str, reply: Reply) -> &Self {
+        self.script
+            .lock()
+            .unwrap()
+            .rules
+            .push((prefix.to_string(), reply));
+        self
+    }
+
+    /// All received statements in the order they arrived.
+    pub fn statements(&self) -> Vec<ReceivedStatement> {
+        self.script.lock().unwrap().statements.clone()
+    }
+
+    /// Take the received statements and clear the record.
+    pub fn take_statements(&self) -> Vec<ReceivedStatement> {
+        std::mem::take(&mut self.script.lock().unwrap().statements)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+const UTF8MB4_GENERAL_CI: u16 = 45;
+const BINARY: u16 = 63;
+
+const COM_QUIT: u8 = 0x01;
+const COM_INIT_DB: u8 = 0x02;
+const COM_QUERY: u8 = 0x03;
+const COM_PING: u8 = 0x0e;
+const COM_CHANGE_USER: u8 = 0x11;
+const COM_STMT_PREPARE: u8 = 0x16;
+const COM_STMT_EXECUTE: u8 = 0x17;
+const COM_STMT_SEND_LONG_DATA: u8 = 0x18;
+const COM_STMT_CLOSE: u8 = 0x19;
+const COM_STMT_RESET: u8 = 0x1a;
+const COM_RESET_CONNECTION: u8 = 0x1f;
+
+const CLIENT_LONG_PASSWORD: u32 = 0x0000_0001;
+const CLIENT_FOUND_ROWS: u32 = 0x0000_0002;
+const CLIENT_LONG_FLAG: u32 = 0x0000_0004;
+const CLIENT_CONNECT_WITH_DB: u32 = 0x0000_0008;
+const CLIENT_PROTOCOL_41: u32 = 0x0000_0200;
+const CLIENT_TRANSACTIONS: u32 = 0x0000_2000;
+const CLIENT_SECURE_CONNECTION: u32 = 0x0000_8000;
+const CLIENT_MULTI_STATEMENTS: u32 = 0x0001_0000;
+const CLIENT_MULTI_RESULTS: u32 = 0x0002_0000;
+const CLIENT_PS_MULTI_RESULTS: u32 = 0x0004_0000;
+const CLIENT_PLUGIN_AUTH: u32 = 0x0008_0000;
+const CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA: u32 = 0x0020_0000;
+
+const SERVER_CAPABILITIES: u32 = CLIENT_LONG_PASSWORD
+    | CLIENT_FOUND_ROWS
+    | CLIENT_LONG_FLAG
+    | CLIENT_CONNECT_WITH_DB
+    | CLIENT_PROTOCOL_41
+    | CLIENT_TRANSACTIONS
+    | CLIENT_SECURE_CONNECTION
+    | CLIENT_MULTI_STATEMENTS
+    | CLIENT_MULTI_RESULTS
+    | CLIENT_PS_MULTI_RESULTS
+    | CLIENT_PLUGIN_AUTH
+    | CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA;
+
+const SERVER_STATUS_AUTOCOMMIT: u16 = 0x0002;
+
+/// Prepared statement of a connection.
+struct Prepared {
+    sql: String,
+    params: usize,
+    types: Vec<(u8, u8)>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Protocol {
+    Text,
+    Binary,
+}
+
+/// Answer the settings query, that the driver sends after connecting.
+fn settings_reply(sql: &str) -> Option<Reply> {
+    let variables = sql.strip_prefix("SELECT @@")?;
+    let mut names = Vec::new();
+    let mut values = Vec::new();
+    for variable in variables.split(",@@") {
+        let value = match variable.trim() {
+            "max_allowed_packet" => Value::Int(16 * 1024 * 1024),
+            "wait_timeout" => Value::Int(28800),
+            "socket" => Value::NULL,
+            _ => return None,
+        };
+        names.push(format!("@@{}", variable.trim()));
+        values.push(value);
+    }
+    let names = names.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+    Some(Reply::ResultSet(ResultSet::new(&names, vec![values])))
+}
+
+async fn serve(
+    mut socket: TcpStream,
+    script: Arc<Mutex<Script>>,
+    connection_id: u32,
+) -> io::Result<()> {
+    let mut writer = PacketWriter::new(0);
+    writer.write(&mut socket, &handshake(connection_id)).await?;
+    let (seq, _handshake_response) = read_packet(&mut socket).await?;
+    // Any user and password is accepted
+    PacketWriter::new(seq.wrapping_add(1))
+        .write(&mut socket, &ok_packet(0, 0))
+        .await?;
+
+    let mut prepared: HashMap<u32, Prepared> = HashMap::new();
+    let mut next_statement_id = 1;
+
+    loop {
+        let (seq, payload) = match read_packet(&mut socket).await {
+            Ok(p) => p,
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        let mut writer = PacketWriter::new(seq.wrapping_add(1));
+        let command = payload.first().copied().unwrap_or_default();
+
+        let (statement, protocol) = match command {
+            COM_QUIT => return Ok(()),
+            COM_PING | COM_INIT_DB | COM_RESET_CONNECTION | COM_CHANGE_USER | COM_STMT_RESET => {
+                writer.write(&mut socket, &ok_packet(0, 0)).await?;
+                continue;
+            }
+            COM_STMT_CLOSE => {
+                if let Some(id) = payload.get(1..5) {
+                    prepared.remove(&u32::from_le_bytes(id.try_into().unwrap()));
+                }
+                continue;
+            }
+            COM_STMT_SEND_LONG_DATA => continue,
+            COM_STMT_PREPARE => {
+                let sql = String::from_utf8_lossy(&payload[1..]).to_string();
+                let params = count_placeholders(&sql);
+                let id = next_statement_id;
+                next_statement_id += 1;
+                prepared.insert(
+                    id,
+                    Prepared {
+                        sql,
+                        params,
+                        types: Vec::new(),
+                    },
+                );
+                write_prepare_ok(&mut socket, &mut writer, id, params).await?;
+                continue;
+            }
+            COM_QUERY => (
+                ReceivedStatement {
+                    sql: String::from_utf8_lossy(&payload[1..]).to_string(),
+                    params: Vec::new(),
+                    prepared: false,
+                },
+                Protocol::Text,
+            ),
+            COM_STMT_EXECUTE => match parse_execute(&payload[1..], &mut prepared) {
+                Some(statement) => (statement, Protocol::Binary),
+                None => {
+                    let err = err_packet(1243, "HY000", "Unknown prepared statement handler");
+                    writer.write(&mut socket, &err).await?;
+                    continue;
+                }
+            },
+            _ => {
+                let err = err_packet(1047, "08S01", "Unknown command");
+                writer.write(&mut socket, &err).await?;
+                continue;
+            }
+        };
+
+        let reply = script.lock().unwrap().reply(statement);
+        match reply {
+            Reply::Ok {
+                affected_rows,
+                last_insert_id,
+            } => {
+                writer
+                    .write(&mut socket, &ok_packet(affected_rows, last_insert_id))
+                    .await?
+            }
+            Reply::Error {
+                code,
+                state,
+                message,
+            } => {
+                writer
+                    .write(&mut socket, &err_packet(code, &state, &message))
+                    .await?
+            }
+            Reply::ResultSet(result_set) => {
+                write_result_set(&mut socket, &mut writer, &result_set, protocol).await?
+            }
+            Reply::Disconnect => return Ok(()),
+        }
+    }
+}
+
+struct PacketWriter {
+    seq: u8,
+}
+
+impl PacketWriter {
+    fn new(seq: u8) -> Self {
+        PacketWriter { seq }
+    }
+
+    async fn write(&mut self, socket: &mut TcpStream, payload: &[u8]) -> io::Result<()> {
+        let mut packet = Vec::with_capacity(payload.len() + 4);
+        packet.extend_from_slice(&(payload.len() as u32).to_le_bytes()[..3]);
+        packet.push(self.seq);
+        packet.extend_from_slice(payload);
+        self.seq = self.seq.wrapping_add(1);
+        socket.write_all(&packet).await
+    }
+}
+
+async fn read_packet(socket: &mut TcpStream) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0u8; 4];
+    socket.read_exact(&mut header).await?;
+    let len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+    let mut payload = vec![0u8; len];
+    socket.read_exact(&mut payload).await?;
+    Ok((header[3], payload))
+}
+
+fn handshake(connection_id: u32) -> Vec<u8> {
+    let mut p = vec![0x0a];
+    p.extend_from_slice(b"8.0.30-toql-test\0");
+    p.extend_from_slice(&connection_id.to_le_bytes());
+    p.extend_from_slice(b"abcdefgh"); // auth plugin data part 1
+    p.push(0);
+    p.extend_from_slice(&(SERVER_CAPABILITIES as u16).to_le_bytes());
+    p.push(UTF8MB4_GENERAL_CI as u8);
+    p.extend_from_slice(&SERVER_STATUS_AUTOCOMMIT.to_le_bytes());
+    p.extend_from_slice(&((SERVER_CAPABILITIES >> 16) as u16).to_le_bytes());
+    p.push(21); // auth plugin data length
+    p.extend_from_slice(&[0u8; 10]);
+    p.extend_from_slice(b"ijklmnopqrst\0"); // auth plugin data part 2
+    p.extend_from_slice(b"mysql_native_password\0");
+    p
+}
+
+fn ok_packet(affected_rows: u64, last_insert_id: u64) -> Vec<u8> {
+    let mut p = vec![0x00];
+    put_lenenc_int(&mut p, affected_rows);
+    put_lenenc_int(&mut p, last_insert_id);
+    p.extend_from_slice(&SERVER_STATUS_AUTOCOMMIT.to_le_bytes());
+    p.extend_from_slice(&0u16.to_le_bytes()); // warnings
+    p
+}
+
+fn eof_packet() -> Vec<u8> {
+    let mut p = vec![0xfe];
+    p.extend_from_slice(&0u16.to_le_bytes()); // warnings
+    p.extend_from_slice(&SERVER_STATUS_AUTOCOMMIT.to_le_bytes());
+    p
+}
+
+fn err_packet(code: u16, state: &str, message: &str) -> Vec<u8> {
+    let mut p = vec![0xff];
+    p.extend_from_slice(&code.to_le_bytes());
+    p.push(b'#');
+    p.extend_from_slice(format!("{:5.5}", state).as_bytes());
+    p.extend_from_slice(message.as_bytes());
+    p
+}
+
+fn column_definition(column: &Column) -> Vec<u8> {
+    let mut p = Vec::new();
+    put_lenenc_str(&mut p, b"def");
+    put_lenenc_str(&mut p, column.schema_ref());
+    put_lenenc_str(&mut p, column.table_ref());
+    put_lenenc_str(&mut p, column.org_table_ref());
+    put_lenenc_str(&mut p, column.name_ref());
+    put_lenenc_str(&mut p, column.org_name_ref());
+    p.push(0x0c);
+    p.extend_from_slice(&column.character_set().to_le_bytes());
+    p.extend_from_slice(&column.column_length().to_le_bytes());
+    p.push(column.column_type() as u8);
+    p.extend_from_slice(&column.flags().bits().to_le_bytes());
+    p.push(column.decimals());
+    p.extend_from_slice(&[0, 0]);
+    p
+}
+
+async fn write_prepare_ok(
+    socket: &mut TcpStream,
+    writer: &mut PacketWriter,
+    id: u32,
+    params: usize,
+) -> io::Result<()> {
+    let mut p = vec![0x00];
+    p.extend_from_slice(&id.to_le_bytes());
+    p.extend_from_slice(&0u16.to_le_bytes()); // columns are sent with the result
+    p.extend_from_slice(&(params as u16).to_le_bytes());
+    p.push(0);
+    p.extend_from_slice(&0u16.to_le_bytes()); // warnings
+    writer.write(socket, &p).await?;
+
+    if params > 0 {
+        let param = Column::new(ColumnType::MYSQL_TYPE_VAR_STRING)
+            .with_name(b"?")
+            .with_character_set(BINARY);
+        for _ in 0..params {
+            writer.write(socket, &column_definition(&param)).await?;
+        }
+        writer.write(socket, &eof_packet()).await?;
+    }
+    Ok(())
+}
+
+async fn write_result_set(
+    socket: &mut TcpStream,
+    writer: &mut PacketWriter,
+    result_set: &ResultSet,
+    protocol: Protocol,
+) -> io::Result<()> {
+    let mut p = Vec::new();
+    put_lenenc_int(&mut p, result_set.columns.len() as u64);
+    writer.write(socket, &p).await?;
+    for column in &result_set.columns {
+        writer.write(socket, &column_definition(column)).await?;
+    }
+    writer.write(socket, &eof_packet()).await?;
+
+    for row in &result_set.rows {
+        let p = match protocol {
+            Protocol::Text => text_row(&result_set.columns, row),
+            Protocol::Binary => binary_row(&result_set.columns, row),
+        };
+        writer.write(socket, &p).await?;
+    }
+    writer.write(socket, &eof_packet()).await
+}
+
+fn text_row(columns: &[Column], row: &[Value]) -> Vec<u8> {
+    let mut p = Vec::new();
+    for (column, value) in columns.iter().zip(row) {
+        match value {
+            Value::NULL => p.push(0xfb),
+            _ => put_lenenc_str(&mut p, &text_value(column.column_type(), value)),
+        }
+    }
+    p
+}
+
+fn text_value(column_type: ColumnType, value: &Value) -> Vec<u8> {
+    match value {
+        Value::NULL => Vec::new(),
+        Value::Bytes(b) => b.clone(),
+        Value::Int(i) => i.to_string().into_bytes(),
+        Value::UInt(u) => u.to_string().into_bytes(),
+        Value::Float(f) => f.to_string().into_bytes(),
+        Value::Double(d) => d.to_string().into_bytes(),
+        Value::Date(y, m, d, h, i, s, us) => {
+            let mut t = format!("{:04}-{:02}-{:02}", y, m, d);
+            if column_type != ColumnType::MYSQL_TYPE_DATE {
+                t.push_str(&format!(" {:02}:{:02}:{:02}", h, i, s));
+                if *us > 0 {
+                    t.push_str(&format!(".{:06}", us));
+                }
+            }
+            t.into_bytes()
+        }
+        Value::Time(neg, d, h, i, s, us) => {
+            let mut t = format!(
+                "{}{:02}:{:02}:{:02}",
+                if *neg { "-" } else { "" },
+                *d * 24 + u32::from(*h),
+                i,
+                s
+            );
+            if *us > 0 {
+                t.push_str(&format!(".{:06}", us));
+            }
+            t.into_bytes()
+        }
+    }
+}
+
+fn binary_row(columns: &[Column], row: &[Value]) -> Vec<u8> {
+    // Null bitmap of binary rows has an offset of 2 bits
+    let mut null_bitmap = vec![0u8; (columns.len() + 7 + 2) / 8];
+    let mut values = Vec::new();
+    for (i, (column, value)) in columns.iter().zip(row).enumerate() {
+        if *value == Value::NULL {
+            null_bitmap[(i + 2) / 8] |= 1 << ((i + 2) % 8);
+        } else {
+            put_binary_value(&mut values, column.column_type(), value);
+        }
+    }
+    let mut p = vec![0x00];
+    p.extend_from_slice(&null_bitmap);
+    p.extend_from_slice(&values);
+    p
+}
+
+fn put_binary_value(p: &mut Vec<u8>, column_type: ColumnType, value: &Value) {
+    let int = || match value {
+        Value::Int(i) => *i,
+        Value::UInt(u) => *u as i64,
+        Value::Float(f) => *f as i64,
+        Value::Double(d) => *d as i64,
+        _ => String::from_utf8_lossy(&text_value(column_type, value))
+            .parse()
+            .unwrap_or_default(),
+    };
+    let float = || match value {
+        Value::Int(i) => *i as f64,
+        Value::UInt(u) => *u as f64,
+        Value::Float(f) => f64::from(*f),
+        Value::Double(d) => *d,
+        _ => String::from_utf8_lossy(&text_value(column_type, value))
+            .parse()
+            .unwrap_or_default(),
+    };
+    match column_type {
+        ColumnType::MYSQL_TYPE_TINY => p.push(int() as u8),
+        ColumnType::MYSQL_TYPE_SHORT | ColumnType::MYSQL_TYPE_YEAR => {
+            p.extend_from_slice(&(int() as u16).to_le_bytes())
+        }
+        ColumnType::MYSQL_TYPE_LONG | ColumnType::MYSQL_TYPE_INT24 => {
+            p.extend_from_slice(&(int() as u32).to_le_bytes())
+        }
+        ColumnType::MYSQL_TYPE_LONGLONG => match value {
+            Value::UInt(u) => p.extend_from_slice(&u.to_le_bytes()),
+            _ => p.extend_from_slice(&int().to_le_bytes()),
+        },
+        ColumnType::MYSQL_TYPE_FLOAT => p.extend_from_slice(&(float() as f32).to_le_bytes()),
+        ColumnType::MYSQL_TYPE_DOUBLE => p.extend_from_slice(&float().to_le_bytes()),
+        ColumnType::MYSQL_TYPE_DATE
+        | ColumnType::MYSQL_TYPE_DATETIME
+        | ColumnType::MYSQL_TYPE_TIMESTAMP => match value {
+            Value::Date(y, m, d, h, i, s, us) => {
+                p.push(11);
+                p.extend_from_slice(&y.to_le_bytes());
+                p.extend_from_slice(&[*m, *d, *h, *i, *s]);
+                p.extend_from_slice(&us.to_le_bytes());
+            }
+            _ => p.push(0),
+        },
+        ColumnType::MYSQL_TYPE_TIME => match value {
+            Value::Time(neg, d, h, i, s, us) => {
+                p.push(12);
+                p.push(*neg as u8);
+                p.extend_from_slice(&d.to_le_bytes());
+                p.extend_from_slice(&[*h, *i, *s]);
+                p.extend_from_slice(&us.to_le_bytes());
+            }
+            _ => p.push(0),
+        },
+        _ => put_lenenc_str(p, &text_value(column_type, value)),
+    }
+}
+
+/// Column type and flags for values without explicit column definition.
+fn column_type_of(value: Option<&Value>) -> (ColumnType, ColumnFlags) {
+    match value {
+        Some(Value::Int(_)) => (ColumnType::MYSQL_TYPE_LONGLONG, ColumnFlags::empty()),
+        Some(Value::UInt(_)) => (
+            ColumnType::MYSQL_TYPE_LONGLONG,
+            ColumnFlags::UNSIGNED_FLAG,
+        ),
+        Some(Value::Float(_)) => (ColumnType::MYSQL_TYPE_FLOAT, ColumnFlags::empty()),
+        Some(Value::Double(_)) => (ColumnType::MYSQL_TYPE_DOUBLE, ColumnFlags::empty()),
+        Some(Value::Date(..)) => (ColumnType::MYSQL_TYPE_DATETIME, ColumnFlags::empty()),
+        Some(Value::Time(..)) => (ColumnType::MYSQL_TYPE_TIME, ColumnFlags::empty()),
+        _ => (ColumnType::MYSQL_TYPE_VAR_STRING, ColumnFlags::empty()),
+    }
+}
+
+/// Parse `COM_STMT_EXECUTE` payload without the command byte.
+fn parse_execute(
+    payload: &[u8],
+    prepared: &mut HashMap<u32, Prepared>,
+) -> Option<ReceivedStatement> {
+    let mut buf = payload;
+    let id = u32::from_le_bytes(take(&mut buf, 4)?.try_into().ok()?);
+    take(&mut buf, 1 + 4)?; // Flags and iteration count
+    let statement = prepared.get_mut(&id)?;
+
+    let mut params = Vec::with_capacity(statement.params);
+    if statement.params > 0 {
+        let null_bitmap = take(&mut buf, (statement.params + 7) / 8)?.to_vec();
+        if take(&mut buf, 1)?[0] == 1 {
+            statement.types = take(&mut buf, statement.params * 2)?
+                .chunks(2)
+                .map(|t| (t[0], t[1]))
+                .collect();
+        }
+        for i in 0..statement.params {
+            if null_bitmap[i / 8] & (1 << (i % 8)) != 0 {
+                params.push(Value::NULL);
+                continue;
+            }
+            let (column_type, flags) = statement.types.get(i).copied().unwrap_or((0xfd, 0));
+            params.push(read_binary_value(&mut buf, column_type, flags & 0x80 != 0)?);
+        }
+    }
+
+    Some(ReceivedStatement {
+        sql: statement.sql.clone(),
+        params,
+        prepared: true,
+    })
+}
+
+fn read_binary_value(buf: &mut &[u8], column_type: u8, unsigned: bool) -> Option<Value> {
+    let int = |bytes: &[u8]| {
+        let mut b = [0u8; 8];
+        b[..bytes.len()].copy_from_slice(bytes);
+        let u = u64::from_le_bytes(b);
+        if unsigned {
+            Value::UInt(u)
+        } else {
+            // Sign extend
+            let shift = 64 - 8 * bytes.len() as u32;
+            Value::Int(((u << shift) as i64) >> shift)
+        }
+    };
+    let value = match column_type {
+        0x06 => Value::NULL,
+        0x01 => int(take(buf, 1)?),
+        0x02 | 0x0d => int(take(buf, 2)?),
+        0x03 | 0x09 => int(take(buf, 4)?),
+        0x08 => int(take(buf, 8)?),
+        0x04 => Value::Float(f32::from_le_bytes(take(buf, 4)?.try_into().ok()?)),
+        0x05 => Value::Double(f64::from_le_bytes(take(buf, 8)?.try_into().ok()?)),
+        0x07 | 0x0a | 0x0c => {
+            let len = take(buf, 1)?[0] as usize;
+            let b = take(buf, len)?;
+            let mut d = [0u8; 11];
+            d[..len].copy_from_slice(b);
+            Value::Date(
+                u16::from_le_bytes([d[0], d[1]]),
+                d[2],
+                d[3],
+                d[4],
+                d[5],
+                d[6],
+                u32::from_le_bytes([d[7], d[8], d[9], d[10]]),
+            )
+        }
+        0x0b => {
+            let len = take(buf, 1)?[0] as usize;
+            let b = take(buf, len)?;
+            let mut t = [0u8; 12];
+            t[..len].copy_from_slice(b);
+            Value::Time(
+                t[0] == 1,
+                u32::from_le_bytes([t[1], t[2], t[3], t[4]]),
+                t[5],
+                t[6],
+                t[7],
+                u32::from_le_bytes([t[8], t[9], t[10], t[11]]),
+            )
+        }
+        _ => {
+            let len = get_lenenc_int(buf)? as usize;
+            Value::Bytes(take(buf, len)?.to_vec())
+        }
+    };
+    Some(value)
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if buf.len() < n {
+        return None;
+    }
+    let (head, tail) = buf.split_at(n);
+    *buf = tail;
+    Some(head)
+}
+
+fn get_lenenc_int(buf: &mut &[u8]) -> Option<u64> {
+    let first = take(buf, 1)?[0];
+    let n = match first {
+        0xfc => 2,
+        0xfd => 3,
+        0xfe => 8,
+        _ => return Some(u64::from(first)),
+    };
+    let mut b = [0u8; 8];
+    b[..n].copy_from_slice(take(buf, n)?);
+    Some(u64::from_le_bytes(b))
+}
+
+fn put_lenenc_int(p: &mut Vec<u8>, v: u64) {
+    if v < 0xfb {
+        p.push(v as u8);
+    } else if v <= 0xffff {
+        p.push(0xfc);
+        p.extend_from_slice(&(v as u16).to_le_bytes());
+    } else if v <= 0xff_ffff {
+        p.push(0xfd);
+        p.extend_from_slice(&(v as u32).to_le_bytes()[..3]);
+    } else {
+        p.push(0xfe);
+        p.extend_from_slice(&v.to_le_bytes());
+    }
+}
+
+fn put_lenenc_str(p: &mut Vec<u8>, s: &[u8]) {
+    put_lenenc_int(p, s.len() as u64);
+    p.extend_from_slice(s);
+}
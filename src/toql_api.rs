@@ -1,9 +1,12 @@
 //! Implementation of [ToqlApi] for MySQL
 //! This allows to use all Toql high level functions with this backend.
 
-use crate::{error::ToqlMySqlAsyncError, queryable::Queryable, row::Row, MySqlAsync};
+use crate::{
+    backend::MySqlAsyncBackend, error::ToqlMySqlAsyncError, queryable::Queryable, row::Row,
+    MySqlAsync,
+};
 use async_trait::async_trait;
-use std::borrow::{Borrow, BorrowMut};
+use std::borrow::{Borrow, BorrowMut, Cow};
 use toql::{
     backend::{count::count, delete::delete, insert::insert, load::load, update::update},
     error::ToqlError,
@@ -12,6 +15,7 @@ use toql::{
     page_counts::PageCounts,
     prelude::{FromRow, Key},
     query::Query,
+    table_mapper::mapped::Mapped,
     toql_api::ToqlApi,
     toql_api::{
         count::Count, delete::Delete, fields::Fields, insert::Insert, load::Load, paths::Paths,
@@ -35,6 +39,7 @@ impl<'a, C> ToqlApi  for $type where C:Queryable + Send
         T: Insert
     {
          insert::<_,_,T,_,_>(&mut self.backend, &mut [entity], paths).await
+            .map_err(|e| e.with_entity("insert into", <T as toql::table_mapper::mapped::Mapped>::type_name()))
     }
 
     /// Insert one struct.
@@ -47,6 +52,7 @@ impl<'a, C> ToqlApi  for $type where C:Queryable + Send
         T: Insert,
         Q: BorrowMut<T> + Send, {
             insert(&mut self.backend, entities, paths).await
+                .map_err(|e| e.with_entity("insert into", <T as toql::table_mapper::mapped::Mapped>::type_name()))
         }
 
    #[tracing::instrument(skip(self, entity, fields), fields(ty = %<T as toql::table_mapper::mapped::Mapped>::type_name()))]
@@ -55,6 +61,7 @@ impl<'a, C> ToqlApi  for $type where C:Queryable + Send
         T: Update + Keyed,
     {
           update::<_,_,T,_,_>(&mut self.backend, &mut [entity], fields).await
+            .map_err(|e| e.with_entity("update", <T as toql::table_mapper::mapped::Mapped>::type_name()))
 
     }
     #[tracing::instrument(skip(self, entities, fields), fields(ty = %<T as toql::table_mapper::mapped::Mapped>::type_name()))]
@@ -64,6 +71,7 @@ impl<'a, C> ToqlApi  for $type where C:Queryable + Send
         Q: BorrowMut<T> + Send + Sync,
     {
             update(&mut self.backend, entities, fields).await
+                .map_err(|e| e.with_entity("update", <T as toql::table_mapper::mapped::Mapped>::type_name()))
     }
 
     /// Load a struct with dependencies for a given Toql query.
@@ -76,7 +84,12 @@ impl<'a, C> ToqlApi  for $type where C:Queryable + Send
         B: Borrow<Query<T>> + Send + Sync,
         <T as Keyed>::Key: FromRow<Self::Row, Self::Error>,
     {
-        let (mut e, _) = load(&mut self.backend, query, Some(Page::Uncounted(0, 2))).await?;
+        let query = convert_query(&self.backend, query.borrow());
+        self.backend.mapper = Some(<T as Mapped>::type_name());
+        let result = load(&mut self.backend, query.as_ref(), Some(Page::Uncounted(0, 2))).await;
+        self.backend.mapper = None;
+        let (mut e, _) = result
+            .map_err(|e| e.with_entity("load", <T as toql::table_mapper::mapped::Mapped>::type_name()))?;
         match e.len() {
             0 => Err(ToqlError::NotFound.into()),
             1 => Ok(e.pop().unwrap()),
@@ -96,7 +109,12 @@ impl<'a, C> ToqlApi  for $type where C:Queryable + Send
         B: Borrow<Query<T>> + Send + Sync,
         <T as Keyed>::Key: FromRow<Self::Row, Self::Error>,
     {
-      let res = load(&mut self.backend, query, None).await?;
+      let query = convert_query(&self.backend, query.borrow());
+      self.backend.mapper = Some(<T as Mapped>::type_name());
+      let res = load(&mut self.backend, query.as_ref(), None).await;
+      self.backend.mapper = None;
+      let res = res
+        .map_err(|e| e.with_entity("load", <T as toql::table_mapper::mapped::Mapped>::type_name()))?;
       Ok(res.0)
     }
 
@@ -112,7 +130,12 @@ impl<'a, C> ToqlApi  for $type where C:Queryable + Send
         B: Borrow<Query<T>> + Send + Sync,
         <T as Keyed>::Key: FromRow<Self::Row, Self::Error>,
     {
-        let entities_page = load(&mut self.backend, query, Some(page)).await?;
+        let query = convert_query(&self.backend, query.borrow());
+        self.backend.mapper = Some(<T as Mapped>::type_name());
+        let entities_page = load(&mut self.backend, query.as_ref(), Some(page)).await;
+        self.backend.mapper = None;
+        let entities_page = entities_page
+            .map_err(|e| e.with_entity("load", <T as toql::table_mapper::mapped::Mapped>::type_name()))?;
 
         Ok(entities_page)
     }
@@ -126,7 +149,9 @@ impl<'a, C> ToqlApi  for $type where C:Queryable + Send
             T: Count,
             B: Borrow<Query<T>> + Send + Sync,
         {
-            count(&mut self.backend, query).await
+            let query = convert_query(&self.backend, query.borrow());
+            count(&mut self.backend, query.as_ref()).await
+                .map_err(|e| e.with_entity("count", <T as toql::table_mapper::mapped::Mapped>::type_name()))
         }
 
     #[tracing::instrument(skip(self, key), fields(ty = %<<K as Key>::Entity as toql::table_mapper::mapped::Mapped>::type_name()))]
@@ -136,7 +161,9 @@ impl<'a, C> ToqlApi  for $type where C:Queryable + Send
 
     {
             let query :Query<<K as Key>::Entity>= key.into();
-            delete(&mut self.backend, query).await?;
+            let query = convert_query(&self.backend, &query);
+            delete(&mut self.backend, query.as_ref()).await
+                .map_err(|e| e.with_entity("delete from", <<K as Key>::Entity as toql::table_mapper::mapped::Mapped>::type_name()))?;
             Ok(())
     }
 
@@ -144,10 +171,30 @@ impl<'a, C> ToqlApi  for $type where C:Queryable + Send
     async fn delete_many<T, B>(&mut self, query: B) -> Result<(), Self::Error>
     where T: Delete, B: Borrow<Query<T>> + Send + Sync,
     <Self as ToqlApi>::Error: From<ToqlError> {
-            delete(&mut self.backend, query).await?;
+            let query = convert_query(&self.backend, query.borrow());
+            delete(&mut self.backend, query.as_ref()).await
+                .map_err(|e| e.with_entity("delete from", <T as toql::table_mapper::mapped::Mapped>::type_name()))?;
              Ok(())
     }
 }
             )+}}
 
 toql_api!(MySqlAsync<'a, C>, &mut MySqlAsync<'a, C>);
+
+/// Query with the filter arguments of registered converters converted.
+fn convert_query<'q, T, C>(backend: &MySqlAsyncBackend<'_, C>, query: &'q Query<T>) -> Cow<'q, Query<T>>
+where
+    T: Mapped,
+    C: Queryable + Send,
+    Query<T>: Clone,
+{
+    match backend
+        .settings
+        .row
+        .converters
+        .convert_query(&<T as Mapped>::type_name(), query)
+    {
+        Some(query) => Cow::Owned(query),
+        None => Cow::Borrowed(query),
+    }
+}
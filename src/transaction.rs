@@ -0,0 +1,232 @@
+//! Transaction scopes on top of [MySqlAsync].
+//!
+//! A scope commits, if its closure returns `Ok` and rolls back otherwise.
+//! Inside a transaction savepoints allow nested scopes, that roll back
+//! to their savepoint without aborting the outer transaction.
+//! Only [retry_transaction](MySqlAsync::retry_transaction) runs a scope again after a deadlock,
+//! following the [RetryPolicy] of the settings, [transaction](MySqlAsync::transaction) runs it once.
+use crate::{
+    backend::MySqlAsyncBackend, error::ToqlMySqlAsyncError, result::Result, retry::RetryPolicy,
+    MySqlAsync,
+};
+use futures_core::future::BoxFuture;
+use mysql_async::{Conn, Transaction, TxOpts};
+
+impl<'a> MySqlAsync<'a, Conn> {
+    /// Run a closure inside a new transaction.
+    ///
+    /// The transaction is committed, if the closure returns `Ok`, and rolled back, if it returns `Err`.
+    /// If the closure panics, the dropped transaction is rolled back by MySqlAsync before
+    /// the connection is used again. Cache, context and settings are shared with the transaction,
+    /// changes of the context inside the closure are kept, once the closure returns.
+    /// The closure is run once, the retry policy is only applied by
+    /// [retry_transaction](Self::retry_transaction).
+    ///
+    /// ```rust
+    /// let tx_opts = TxOpts::default();
+    /// toql.transaction(tx_opts, |tx| Box::pin(async move {
+    ///     tx.insert_many(&mut payments, paths!(top)).await
+    /// })).await?;
+    /// ```
+    pub async fn transaction<F, R>(&mut self, opts: TxOpts, f: F) -> Result<R>
+    where
+        F: for<'t, 'c> FnOnce(&'t mut MySqlAsync<'a, Transaction<'c>>) -> BoxFuture<'t, Result<R>>,
+        R: Send,
+    {
+        self.run_transaction(opts, f).await.map_err(|e| e.error)
+    }
+
+    /// Run a closure inside a new transaction and run it again after a deadlock or lock wait timeout.
+    ///
+    /// The attempts and the delays between them are controlled by the
+    /// [RetryPolicy](crate::retry::RetryPolicy) of the settings, without policy the closure is run once.
+    /// A transaction is only run again, if it is known to be rolled back entirely.
+    /// Every retry is recorded as tracing event.
+    ///
+    /// ```rust
+    /// toql.retry_transaction(TxOpts::default(), |tx| Box::pin(async move {
+    ///     tx.update_many(&mut payments, fields!(top)).await
+    /// })).await?;
+    /// ```
+    pub async fn retry_transaction<F, R>(&mut self, opts: TxOpts, mut f: F) -> Result<R>
+    where
+        F: for<'t, 'c> FnMut(&'t mut MySqlAsync<'a, Transaction<'c>>) -> BoxFuture<'t, Result<R>>,
+        R: Send,
+    {
+        let policy = self
+            .backend
+            .settings
+            .retry_policy
+            .clone()
+            .unwrap_or_else(RetryPolicy::never);
+        let mut attempt = 1;
+        loop {
+            match self.run_transaction(opts.clone(), &mut f).await {
+                Ok(r) => return Ok(r),
+                Err(TransactionError {
+                    error,
+                    rolled_back: true,
+                }) if error.is_retryable() && attempt < policy.max_attempts => {
+                    let delay = policy.backoff(attempt);
+                    tracing::warn!(
+                        attempt,
+                        max_attempts = policy.max_attempts,
+                        delay_ms = delay.as_millis() as u64,
+                        "Retrying transaction after: {}",
+                        error
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.error),
+            }
+        }
+    }
+
+    async fn run_transaction<F, R>(
+        &mut self,
+        opts: TxOpts,
+        f: F,
+    ) -> std::result::Result<R, TransactionError>
+    where
+        F: for<'t, 'c> FnOnce(&'t mut MySqlAsync<'a, Transaction<'c>>) -> BoxFuture<'t, Result<R>>,
+        R: Send,
+    {
+        let cache = self.backend.cache;
+        // The transaction works on a copy of the context, so the context is kept,
+        // if the future is dropped or the closure panics
+        let context = self.backend.context.clone();
+        let settings = self.backend.settings.clone();
+        let tx = self
+            .backend
+            .conn
+            .start_transaction(opts)
+            .await
+            .map_err(|err| TransactionError::rolled_back(err.into()))?;
+
+        let mut toql = MySqlAsync::with_settings(tx, cache, context, settings);
+        let result = f(&mut toql).await;
+
+        let MySqlAsyncBackend {
+            conn: tx, context, ..
+        } = toql.backend;
+        self.backend.context = context;
+
+        match result {
+            Ok(r) => match tx.commit().await {
+                Ok(()) => Ok(r),
+                Err(err) => {
+                    // The server rolls back a transaction, that fails with a deadlock on commit.
+                    // On any other failure the outcome of the commit is unknown.
+                    let error = ToqlMySqlAsyncError::from(err);
+                    let rolled_back = error.is_retryable();
+                    Err(TransactionError { error, rolled_back })
+                }
+            },
+            Err(error) => {
+                // Keep the error from the closure, it's more meaningful
+                match tx.rollback().await {
+                    Ok(()) => Err(TransactionError::rolled_back(error)),
+                    Err(rollback_err) => {
+                        tracing::warn!("Rollback failed: {}", rollback_err);
+                        Err(TransactionError {
+                            error,
+                            rolled_back: false,
+                        })
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Failed transaction.
+struct TransactionError {
+    error: ToqlMySqlAsyncError,
+    /// True, if nothing of the transaction was committed
+    rolled_back: bool,
+}
+
+impl TransactionError {
+    fn rolled_back(error: ToqlMySqlAsyncError) -> Self {
+        TransactionError {
+            error,
+            rolled_back: true,
+        }
+    }
+}
+
+impl<'a, 'c> MySqlAsync<'a, Transaction<'c>> {
+    /// Set a savepoint with the given name.
+    ///
+    /// An existing savepoint with the same name is replaced.
+    pub async fn savepoint(&mut self, name: &str) -> Result<()> {
+        self.savepoint_sql(format!("SAVEPOINT {}", quote_identifier(name)))
+            .await
+    }
+
+    /// Roll back all changes made after the named savepoint.
+    ///
+    /// The savepoint is kept and can be rolled back to again.
+    pub async fn rollback_to(&mut self, name: &str) -> Result<()> {
+        self.savepoint_sql(format!(
+            "ROLLBACK TO SAVEPOINT {}",
+            quote_identifier(name)
+        ))
+        .await
+    }
+
+    /// Remove the named savepoint without changing any data.
+    pub async fn release(&mut self, name: &str) -> Result<()> {
+        self.savepoint_sql(format!("RELEASE SAVEPOINT {}", quote_identifier(name)))
+            .await
+    }
+
+    /// Run a closure inside a savepoint.
+    ///
+    /// If the closure returns `Err`, all its changes are rolled back to the savepoint
+    /// and the error is returned. The outer transaction stays usable.
+    /// If the rollback fails too, both errors are returned in a
+    /// [RollbackError](ToqlMySqlAsyncError::RollbackError).
+    ///
+    /// ```rust
+    /// let r = tx.nested("payments", |tx| Box::pin(async move {
+    ///     tx.insert_many(&mut payments, paths!(top)).await
+    /// })).await;
+    /// ```
+    pub async fn nested<F, R>(&mut self, name: &str, f: F) -> Result<R>
+    where
+        F: for<'t> FnOnce(&'t mut MySqlAsync<'a, Transaction<'c>>) -> BoxFuture<'t, Result<R>>,
+        R: Send,
+    {
+        self.savepoint(name).await?;
+        match f(self).await {
+            Ok(r) => {
+                self.release(name).await?;
+                Ok(r)
+            }
+            Err(err) => {
+                let rollback = match self.rollback_to(name).await {
+                    Ok(()) => self.release(name).await,
+                    Err(rollback) => Err(rollback),
+                };
+                match rollback {
+                    Ok(()) => Err(err),
+                    Err(rollback) => Err(ToqlMySqlAsyncError::RollbackError {
+                        error: Box::new(err),
+                        rollback: Box::new(rollback),
+                    }),
+                }
+            }
+        }
+    }
+
+    async fn savepoint_sql(&mut self, stmt: String) -> Result<()> {
+        self.backend.literal_sql(&stmt).await
+    }
+}
+
+/// Quote savepoint name as MySQL identifier.
+fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}